@@ -0,0 +1,292 @@
+use crate::ascii_grid::{AsciiCell, AsciiGrid, TextStyle, WIDE_CONTINUATION};
+use crate::color_depth::palette_256;
+use image::Rgb;
+use unicode_width::UnicodeWidthChar;
+
+// 制表符对齐的列宽
+const TAB_WIDTH: usize = 8;
+
+// SGR（Select Graphic Rendition）序列设置的文本属性，在整段文本中持续生效直到被修改或重置
+#[derive(Debug, Clone, Copy, Default)]
+struct SgrState {
+    foreground: Option<Rgb<u8>>,
+    background: Option<Rgb<u8>>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    inverse: bool,
+}
+
+impl SgrState {
+    // 应用一个 SGR 序列的参数（`ESC [` 与 `m` 之间的部分）
+    fn apply(&mut self, params: &str) {
+        let mut params = params.split(';');
+        while let Some(param) = params.next() {
+            // 冒号分隔的子参数形式，如 `38:2::r:g:b` 或 `4:3`
+            if param.contains(':') {
+                self.apply_subparams(&param.split(':').collect::<Vec<_>>());
+                continue;
+            }
+
+            let Ok(code) = (if param.is_empty() { Ok(0) } else { param.parse::<u8>() }) else {
+                continue;
+            };
+            match code {
+                0 => *self = SgrState::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 | 21 => self.underline = true,
+                7 => self.inverse = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                30..=37 => self.foreground = Some(palette_256(code - 30)),
+                38 => self.foreground = extended_color(&mut params),
+                39 => self.foreground = None,
+                40..=47 => self.background = Some(palette_256(code - 40)),
+                48 => self.background = extended_color(&mut params),
+                49 => self.background = None,
+                // 下划线颜色不影响渲染，只需跳过其参数
+                58 => {
+                    extended_color(&mut params);
+                }
+                90..=97 => self.foreground = Some(palette_256(code - 90 + 8)),
+                100..=107 => self.background = Some(palette_256(code - 100 + 8)),
+                _ => {}
+            }
+        }
+    }
+
+    fn apply_subparams(&mut self, subparams: &[&str]) {
+        match subparams[0] {
+            "4" => self.underline = subparams.get(1).is_none_or(|&style| style != "0"),
+            code @ ("38" | "48") => {
+                // 真彩色的色彩空间标识可以省略: `38:2:r:g:b` 与 `38:2::r:g:b` 等价
+                let rest = match subparams {
+                    [_, "2", _, r, g, b] => vec!["2", r, g, b],
+                    _ => subparams[1..].to_vec(),
+                };
+                let color = extended_color(&mut rest.into_iter());
+                if code == "38" {
+                    self.foreground = color;
+                } else {
+                    self.background = color;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // 按当前属性生成单元格：反显交换前景色和背景色，暗淡将前景色向背景色减半
+    fn cell(&self, glyph: char, default_foreground: Rgb<u8>, default_background: Rgb<u8>) -> AsciiCell {
+        let (mut foreground, mut background) = (self.foreground, self.background);
+        if self.inverse {
+            (foreground, background) = (
+                Some(background.unwrap_or(default_background)),
+                Some(foreground.unwrap_or(default_foreground)),
+            );
+        }
+        if self.dim {
+            let fg = foreground.unwrap_or(default_foreground);
+            let bg = background.unwrap_or(default_background);
+            foreground = Some(Rgb([0, 1, 2].map(|c| ((fg[c] as u16 + bg[c] as u16) / 2) as u8)));
+        }
+
+        AsciiCell {
+            foreground,
+            background,
+            style: TextStyle { bold: self.bold, italic: self.italic, underline: self.underline },
+            ..AsciiCell::new(glyph)
+        }
+    }
+}
+
+// 解析 `5;n`（256 色）或 `2;r;g;b`（真彩色）形式的扩展颜色参数
+fn extended_color<'a>(params: &mut impl Iterator<Item = &'a str>) -> Option<Rgb<u8>> {
+    let mut next = || params.next().and_then(|p| p.parse::<u8>().ok());
+    match next()? {
+        5 => next().map(palette_256),
+        2 => {
+            let (r, g, b) = (next(), next(), next());
+            Some(Rgb([r?, g?, b?]))
+        }
+        _ => None,
+    }
+}
+
+/// 将带 ANSI 转义序列的文本解析为字符网格
+///
+/// 支持 16 色、256 色和真彩色的前景色与背景色，以及粗体、暗淡、斜体、下划线和反显属性；
+/// 其余转义序列被忽略且不占用列。属性跨行保持，直到被修改或重置。
+/// `default_foreground` 和 `default_background` 用于计算反显和暗淡的颜色。
+pub fn parse_ansi_text(text: &str, default_foreground: Rgb<u8>, default_background: Rgb<u8>) -> AsciiGrid {
+    let mut rows: Vec<Vec<AsciiCell>> = Vec::new();
+    let mut state = SgrState::default();
+
+    for line in text.lines() {
+        let mut row: Vec<AsciiCell> = Vec::new();
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1B' => match chars.next() {
+                    // CSI 序列：参数字节后跟一个结束字节，只有 `m` 设置文本属性
+                    Some('[') => {
+                        let mut params = String::new();
+                        for next_c in chars.by_ref() {
+                            if ('\x40'..='\x7E').contains(&next_c) {
+                                if next_c == 'm' {
+                                    state.apply(&params);
+                                }
+                                break;
+                            }
+                            params.push(next_c);
+                        }
+                    }
+                    // OSC 序列：以 BEL 或 `ESC \` 结束
+                    Some(']') => {
+                        while let Some(next_c) = chars.next() {
+                            if next_c == '\x07' || (next_c == '\x1B' && chars.next_if_eq(&'\\').is_some()) {
+                                break;
+                            }
+                        }
+                    }
+                    // nF 序列（如选择字符集的 `ESC ( B`）：中间字节后跟一个结束字节
+                    Some(' '..='/') => {
+                        while chars.next_if(|c| (' '..='/').contains(c)).is_some() {}
+                        chars.next_if(|c| ('0'..='~').contains(c));
+                    }
+                    // 其余两字节的转义序列
+                    _ => {}
+                },
+                '\t' => {
+                    let spaces = TAB_WIDTH - row.len() % TAB_WIDTH;
+                    row.extend(std::iter::repeat_n(state.cell(' ', default_foreground, default_background), spaces));
+                }
+                // 控制字符和组合字符等零宽字符不占用列
+                c if c.is_control() || c.width() == Some(0) => {}
+                c => {
+                    let cell = state.cell(c, default_foreground, default_background);
+                    row.push(cell);
+                    // 宽字符占用两列
+                    if cell.display_width() == 2 {
+                        row.push(AsciiCell { glyph: WIDE_CONTINUATION, ..cell });
+                    }
+                }
+            }
+        }
+
+        rows.push(row);
+    }
+
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+    let mut grid = AsciiGrid::new(width, rows.len() as u32);
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, cell) in row.into_iter().enumerate() {
+            grid.set(x as u32, y as u32, cell);
+        }
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FG: Rgb<u8> = Rgb([200, 200, 200]);
+    const BG: Rgb<u8> = Rgb([0, 0, 0]);
+
+    fn state(params: &str) -> SgrState {
+        let mut state = SgrState::default();
+        state.apply(params);
+        state
+    }
+
+    fn row_text(grid: &AsciiGrid, y: u32) -> String {
+        (0..grid.width()).filter_map(|x| grid.get(x, y)).map(|c| c.glyph).collect()
+    }
+
+    #[test]
+    fn parses_extended_colors() {
+        assert_eq!(state("38;5;196").foreground, Some(palette_256(196)));
+        assert_eq!(state("48;5;21").background, Some(palette_256(21)));
+        assert_eq!(state("38;2;10;20;30").foreground, Some(Rgb([10, 20, 30])));
+        assert_eq!(state("48;2;40;50;60").background, Some(Rgb([40, 50, 60])));
+        // 扩展颜色之后的参数继续生效
+        let s = state("38;5;2;1");
+        assert_eq!(s.foreground, Some(palette_256(2)));
+        assert!(s.bold);
+        // 参数不完整时不设置颜色
+        assert_eq!(state("38;2;10;20").foreground, None);
+    }
+
+    #[test]
+    fn parses_colon_subparameters() {
+        assert_eq!(state("38:2::10:20:30").foreground, Some(Rgb([10, 20, 30])));
+        assert_eq!(state("38:2:10:20:30").foreground, Some(Rgb([10, 20, 30])));
+        assert_eq!(state("48:5:33").background, Some(palette_256(33)));
+        assert!(state("4:3").underline);
+        assert!(!state("4;4:0").underline);
+    }
+
+    #[test]
+    fn applies_attributes_and_reset() {
+        let s = state("1;2;3;4;7");
+        assert!(s.bold && s.dim && s.italic && s.underline && s.inverse);
+
+        // 22 同时关闭粗体和暗淡
+        let mut s = state("1;2;31");
+        s.apply("22");
+        assert!(!s.bold && !s.dim);
+        assert_eq!(s.foreground, Some(palette_256(1)));
+
+        // 空参数等同于 0
+        s.apply("");
+        assert_eq!(s.foreground, None);
+        let mut s = state("1;31;42");
+        s.apply("0");
+        assert!(!s.bold);
+        assert_eq!((s.foreground, s.background), (None, None));
+    }
+
+    #[test]
+    fn inverse_and_dim_adjust_cell_colors() {
+        let cell = state("7").cell('x', FG, BG);
+        assert_eq!((cell.foreground, cell.background), (Some(BG), Some(FG)));
+
+        let cell = state("31;44;7").cell('x', FG, BG);
+        assert_eq!((cell.foreground, cell.background), (Some(palette_256(4)), Some(palette_256(1))));
+
+        let cell = state("2;38;2;200;100;50;48;2;0;0;0").cell('x', FG, BG);
+        assert_eq!(cell.foreground, Some(Rgb([100, 50, 25])));
+    }
+
+    #[test]
+    fn expands_tabs_to_eight_columns() {
+        let grid = parse_ansi_text("a\tb\n\tc", FG, BG);
+        assert_eq!(row_text(&grid, 0).trim_end(), "a       b");
+        assert_eq!(row_text(&grid, 1).trim_end(), "        c");
+    }
+
+    #[test]
+    fn skips_escape_sequences_without_columns() {
+        // OSC 序列以 BEL 或 ST 结束
+        let grid = parse_ansi_text("A\x1B]0;title\x07B\x1B]8;;http://x\x1B\\C", FG, BG);
+        assert_eq!(row_text(&grid, 0), "ABC");
+
+        // `tput sgr0` 输出的 nF 序列 `ESC ( B`
+        let grid = parse_ansi_text("A\x1B(B\x1B[mZ", FG, BG);
+        assert_eq!(row_text(&grid, 0), "AZ");
+
+        // 非 SGR 的 CSI 序列和两字节转义序列
+        let grid = parse_ansi_text("A\x1B[2KB\x1B=C\x1B[31mD", FG, BG);
+        assert_eq!(row_text(&grid, 0), "ABCD");
+        assert_eq!(grid.get(3, 0).unwrap().foreground, Some(palette_256(1)));
+    }
+}
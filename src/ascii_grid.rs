@@ -0,0 +1,304 @@
+use crate::color_depth::ColorDepth;
+use image::Rgb;
+use std::fmt;
+use unicode_width::UnicodeWidthChar;
+
+pub const ANSI_RESET: &str = "\x1b[0m";
+
+/// 宽字符右半列占位单元格使用的字符，输出时跳过
+pub const WIDE_CONTINUATION: char = '\0';
+
+/// 字符的字体样式，目前只在渲染图像时生效
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+/// 字符画中的单个字符单元格
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AsciiCell {
+    /// 显示的字符
+    pub glyph: char,
+    /// 前景色，None 表示使用输出端的默认前景色
+    pub foreground: Option<Rgb<u8>>,
+    /// 背景色，None 表示使用输出端的默认背景色
+    pub background: Option<Rgb<u8>>,
+    /// 单元格对应源图像区域的亮度 (0-255)
+    pub luminance: u8,
+    /// 单元格的不透明度，0 表示完全透明
+    pub alpha: u8,
+    pub style: TextStyle,
+}
+
+impl AsciiCell {
+    pub fn new(glyph: char) -> Self {
+        AsciiCell {
+            glyph,
+            foreground: None,
+            background: None,
+            luminance: 0,
+            alpha: 255,
+            style: TextStyle::default(),
+        }
+    }
+}
+
+impl AsciiCell {
+    /// 是否为宽字符右半列的占位单元格
+    pub fn is_continuation(&self) -> bool {
+        self.glyph == WIDE_CONTINUATION
+    }
+
+    /// 字符在终端中占用的列数，全角字符和 emoji 为 2，占位单元格为 0
+    pub fn display_width(&self) -> u32 {
+        if self.is_continuation() {
+            0
+        } else {
+            self.glyph.width().unwrap_or(1).max(1) as u32
+        }
+    }
+}
+
+impl Default for AsciiCell {
+    fn default() -> Self {
+        AsciiCell::new(' ')
+    }
+}
+
+/// 由字符单元格组成的二维网格，是所有输出格式共用的中间表示
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsciiGrid {
+    width: u32,
+    height: u32,
+    cells: Vec<AsciiCell>,
+}
+
+impl AsciiGrid {
+    /// 创建一个以空格填充的网格
+    pub fn new(width: u32, height: u32) -> Self {
+        AsciiGrid {
+            width,
+            height,
+            cells: vec![AsciiCell::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<&AsciiCell> {
+        if x < self.width && y < self.height {
+            self.cells.get((y * self.width + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// 设置单元格，宽字符同时占用右侧一列，该列被设为与其颜色相同的占位单元格
+    pub fn set(&mut self, x: u32, y: u32, cell: AsciiCell) {
+        if x < self.width && y < self.height {
+            self.cells[(y * self.width + x) as usize] = cell;
+            if cell.display_width() == 2 && x + 1 < self.width {
+                self.cells[(y * self.width + x + 1) as usize] = AsciiCell { glyph: WIDE_CONTINUATION, ..cell };
+            }
+        }
+    }
+
+    /// 按行遍历所有单元格
+    pub fn rows(&self) -> impl Iterator<Item = &[AsciiCell]> {
+        self.cells.chunks(self.width.max(1) as usize)
+    }
+
+    /// 是否有任意单元格带有颜色信息
+    pub fn has_color(&self) -> bool {
+        self.cells.iter().any(|c| c.foreground.is_some() || c.background.is_some())
+    }
+
+    /// 不含任何 ANSI 转义序列的纯文本表示
+    pub fn to_plain_text(&self) -> String {
+        let mut text = String::with_capacity(self.cells.len() + self.height as usize);
+        for row in self.rows() {
+            text.extend(row.iter().filter(|c| !c.is_continuation()).map(|c| c.glyph));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// 按指定颜色深度输出带 ANSI 颜色转义序列的文本，无颜色的单元格按纯文本输出
+    ///
+    /// 仅在颜色发生变化时输出新的 SGR 序列，每个通道与当前颜色相差不超过 `tolerance`
+    /// 的颜色视为相同；每行末尾输出一次重置序列
+    pub fn to_ansi_text(&self, depth: ColorDepth, tolerance: u8) -> String {
+        if depth == ColorDepth::None {
+            return self.to_plain_text();
+        }
+
+        let mut text = String::new();
+        for row in self.rows() {
+            // 终端当前生效的前景色和背景色
+            let mut current_fg: Option<(Rgb<u8>, String)> = None;
+            let mut current_bg: Option<(Rgb<u8>, String)> = None;
+
+            for cell in row.iter().filter(|c| !c.is_continuation()) {
+                let fg = cell.foreground.and_then(|c| depth.foreground_sgr(c).map(|sgr| (c, sgr)));
+                let bg = cell.background.and_then(|c| depth.background_sgr(c).map(|sgr| (c, sgr)));
+
+                // 需要取消已生效的颜色时只能整体重置
+                if (current_fg.is_some() && fg.is_none()) || (current_bg.is_some() && bg.is_none()) {
+                    text.push_str(ANSI_RESET);
+                    current_fg = None;
+                    current_bg = None;
+                }
+
+                if let Some(fg) = fg
+                    && !Self::same_color(current_fg.as_ref(), &fg, tolerance) {
+                    text.push_str(&fg.1);
+                    current_fg = Some(fg);
+                }
+                if let Some(bg) = bg
+                    && !Self::same_color(current_bg.as_ref(), &bg, tolerance) {
+                    text.push_str(&bg.1);
+                    current_bg = Some(bg);
+                }
+
+                text.push(cell.glyph);
+            }
+
+            if current_fg.is_some() || current_bg.is_some() {
+                text.push_str(ANSI_RESET);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn same_color(current: Option<&(Rgb<u8>, String)>, next: &(Rgb<u8>, String), tolerance: u8) -> bool {
+        match current {
+            Some((color, sgr)) => {
+                *sgr == next.1 || color.0.iter().zip(next.0.0).all(|(&a, b)| a.abs_diff(b) <= tolerance)
+            }
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for AsciiGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ansi_text(ColorDepth::TrueColor, 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ascii_mapping::{AsciiConfig, AsciiMapper};
+
+    fn colored(glyph: char, color: [u8; 3]) -> AsciiCell {
+        AsciiCell { foreground: Some(Rgb(color)), ..AsciiCell::new(glyph) }
+    }
+
+    // 每行由两段长的同色片段组成
+    fn run_grid() -> AsciiGrid {
+        let mut grid = AsciiGrid::new(40, 3);
+        for y in 0..3 {
+            for x in 0..40 {
+                let color = if x < 20 { [200, 10, 10] } else { [10, 10, 200] };
+                grid.set(x, y, colored('#', color));
+            }
+        }
+        grid
+    }
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().collect()
+    }
+
+    #[test]
+    fn runs_are_smaller_than_one_sgr_per_cell() {
+        // 上下渐变、左右带色相变化的样例图像
+        let img = image::RgbImage::from_fn(320, 200, |x, y| {
+            let v = (y * 255 / 199) as u8;
+            Rgb([v, (x * 255 / 319) as u8 / 4 + v / 2, 255 - v])
+        });
+        let config = AsciiConfig { width: 80, color: true, ..AsciiConfig::default() };
+        let grid = AsciiMapper::new(config).image_to_grid(&image::DynamicImage::ImageRgb8(img)).unwrap();
+        let text = grid.to_ansi_text(ColorDepth::TrueColor, 0);
+
+        // 每个单元格单独设置颜色并重置的旧编码
+        let per_cell: String = grid
+            .rows()
+            .map(|row| {
+                let mut line: String = row
+                    .iter()
+                    .filter(|c| !c.is_continuation())
+                    .map(|c| match c.foreground {
+                        Some(Rgb([r, g, b])) => format!("\x1b[38;2;{r};{g};{b}m{}{ANSI_RESET}", c.glyph),
+                        None => c.glyph.to_string(),
+                    })
+                    .collect();
+                line.push('\n');
+                line
+            })
+            .collect();
+
+        assert!(text.len() < per_cell.len(), "{} >= {}", text.len(), per_cell.len());
+        assert_eq!(lines(&text).len(), grid.height() as usize);
+        for line in lines(&text) {
+            assert!(line.ends_with(ANSI_RESET));
+            assert!(!line.ends_with(&ANSI_RESET.repeat(2)));
+            assert_eq!(line.matches(ANSI_RESET).count(), 1);
+        }
+    }
+
+    #[test]
+    fn tolerance_merges_near_colors() {
+        let mut grid = AsciiGrid::new(2, 1);
+        grid.set(0, 0, colored('a', [100, 100, 100]));
+        grid.set(1, 0, colored('b', [103, 98, 100]));
+
+        let exact = grid.to_ansi_text(ColorDepth::TrueColor, 0);
+        let merged = grid.to_ansi_text(ColorDepth::TrueColor, 4);
+        assert_eq!(exact.matches("\x1b[38;2;").count(), 2);
+        assert_eq!(merged.matches("\x1b[38;2;").count(), 1);
+        assert!(merged.len() < exact.len());
+    }
+
+    #[test]
+    fn every_colored_row_ends_with_one_reset() {
+        let text = run_grid().to_ansi_text(ColorDepth::TrueColor, 0);
+        for line in lines(&text) {
+            assert!(line.ends_with(ANSI_RESET));
+            assert!(!line.ends_with(&ANSI_RESET.repeat(2)));
+            assert_eq!(line.matches(ANSI_RESET).count(), 1);
+        }
+    }
+
+    #[test]
+    fn uncolored_cell_after_color_resets() {
+        let mut grid = AsciiGrid::new(3, 1);
+        grid.set(0, 0, colored('a', [255, 0, 0]));
+        grid.set(1, 0, AsciiCell::new('b'));
+        grid.set(2, 0, colored('c', [255, 0, 0]));
+
+        let text = grid.to_ansi_text(ColorDepth::TrueColor, 0);
+        let line = lines(&text)[0];
+        // 无颜色的单元格前必须重置，之后的同色单元格需要重新设置颜色
+        assert!(line.contains(&format!("a{ANSI_RESET}b")));
+        assert_eq!(line.matches("\x1b[38;2;255;0;0m").count(), 2);
+        assert!(line.ends_with(&format!("c{ANSI_RESET}")));
+        assert!(!line.ends_with(&ANSI_RESET.repeat(2)));
+    }
+
+    #[test]
+    fn uncolored_rows_have_no_escape_sequences() {
+        let text = AsciiGrid::new(5, 2).to_ansi_text(ColorDepth::TrueColor, 0);
+        assert_eq!(text, "     \n     \n");
+    }
+}
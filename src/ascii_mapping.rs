@@ -1,15 +1,29 @@
 use std::string::String;
-use std::error::Error;
-use image::{GenericImageView, Pixel};
-
-const ANSI_RESET: &str = "\x1b[0m";
+use std::sync::OnceLock;
+use image::{DynamicImage, Rgb, Rgba};
+use unicode_width::UnicodeWidthChar;
+use crate::ascii_grid::{AsciiCell, AsciiGrid};
+use crate::ascii_to_image::default_cell_aspect_ratio;
+use crate::color_depth::ColorDepth;
+use crate::block_elements::{self, HALF_BLOCKS, QUADRANTS};
+use crate::custom_charset_util::CharsetAnalyzer;
+use crate::dithering::{self, DitherMode};
+use crate::edge_detection;
+use crate::luminance::{self, LuminanceModel};
+use crate::resampling::{self, ResampleFilter};
+use crate::tone_mapping::ToneMapping;
+use crate::font_chain::FontChain;
+use crate::glyph_matching::{GlyphMatcher, MATRIX_COLS, MATRIX_ROWS};
+use crate::error::{AsciiArtError, Result};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Charset {
     SIMPLE,
     DEFAULT,
     BLOCK,
     PIXEL,
+    BRAILLE,
     CUSTOM,
 }
 
@@ -20,34 +34,177 @@ impl Charset {
             Charset::DEFAULT => " .'`^\",:;Il!i><~+_-?][}{1)(|\\/tfjrxnuvczXYUJCLQ0OZmwqpdbkhao*#MW&8%B@$",
             Charset::BLOCK => "▁▂▃▄▅▆▇█",
             Charset::PIXEL => "█",
+            Charset::BRAILLE => "⠀⠁⠃⠇⡇⣇⣧⣷⣿",
             Charset::CUSTOM => "",
         }
     }
 }
 
 impl std::str::FromStr for Charset {
-    type Err = String;
+    type Err = AsciiArtError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self> {
         match s.to_uppercase().as_str() {
             "DEFAULT" => Ok(Charset::DEFAULT),
             "SIMPLE" => Ok(Charset::SIMPLE),
             "BLOCK" => Ok(Charset::BLOCK),
             "PIXEL" => Ok(Charset::PIXEL),
-            _ => Err(format!("不支持或未定义的字符集: {s}"))
+            "BRAILLE" => Ok(Charset::BRAILLE),
+            _ => Err(AsciiArtError::InvalidCharset(format!("不支持或未定义的字符集: {s}")))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// 按单元格平均亮度选择字符
+    #[default]
+    Density,
+    /// 按单元格内的亮度分布与字符形状匹配选择字符
+    Structural,
+    /// 使用 ▀/▄ 半块字符，每个单元格表示上下两个像素
+    HalfBlock,
+    /// 使用 2x2 象限块字符
+    Quadrant,
+    /// 使用 2x3 六分块字符
+    Sextant,
+}
+
+impl RenderMode {
+    /// 每个字符单元格包含的子像素列数和行数
+    pub fn cell_resolution(&self) -> (u32, u32) {
+        match self {
+            RenderMode::Density | RenderMode::Structural => (1, 1),
+            RenderMode::HalfBlock => (1, 2),
+            RenderMode::Quadrant => (2, 2),
+            RenderMode::Sextant => (2, 3),
+        }
+    }
+
+    /// 是否为使用块元素字符的高分辨率模式
+    pub fn is_block(&self) -> bool {
+        self.cell_resolution() != (1, 1)
+    }
+
+    fn block_glyph(&self, mask: u8) -> char {
+        match self {
+            RenderMode::HalfBlock => HALF_BLOCKS[(mask & 0x3) as usize],
+            RenderMode::Quadrant => QUADRANTS[(mask & 0xF) as usize],
+            _ => block_elements::sextant(mask),
+        }
+    }
+}
+
+impl std::str::FromStr for RenderMode {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "density" => Ok(RenderMode::Density),
+            "structural" => Ok(RenderMode::Structural),
+            "half-block" | "halfblock" => Ok(RenderMode::HalfBlock),
+            "quadrant" => Ok(RenderMode::Quadrant),
+            "sextant" => Ok(RenderMode::Sextant),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的渲染模式: {s}")))
+        }
+    }
+}
+
+/// 彩色输出时单元格颜色的使用方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// 以单元格颜色作为字符的前景色
+    #[default]
+    Foreground,
+    /// 以单元格颜色填充背景，字符使用默认前景色
+    Background,
+    /// 以单元格颜色填充背景，字符使用同色调的对比色
+    Combined,
+}
+
+impl std::str::FromStr for ColorMode {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "foreground" | "fg" => Ok(ColorMode::Foreground),
+            "background" | "bg" => Ok(ColorMode::Background),
+            "combined" => Ok(ColorMode::Combined),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的颜色模式: {s}")))
         }
     }
 }
 
+/// 透明像素的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// 按不透明度与指定的底色混合
+    Matte(Rgb<u8>),
+    /// 保留透明度，完全透明的单元格输出为空白
+    Transparent,
+}
+
+impl Default for AlphaMode {
+    fn default() -> Self {
+        AlphaMode::Matte(Rgb([0, 0, 0]))
+    }
+}
+
+impl std::str::FromStr for AlphaMode {
+    type Err = AsciiArtError;
+
+    /// 接受 `transparent` 或 `#rrggbb` 形式的底色
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("transparent") || s.eq_ignore_ascii_case("none") {
+            return Ok(AlphaMode::Transparent);
+        }
+
+        parse_hex_color(s)
+            .map(AlphaMode::Matte)
+            .ok_or_else(|| AsciiArtError::InvalidArgument(format!("无效的底色: {s}")))
+    }
+}
+
+/// 解析 `#rrggbb`（`#` 可省略）形式的颜色
+pub fn parse_hex_color(s: &str) -> Option<Rgb<u8>> {
+    let hex = s.trim_start_matches('#');
+    let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Some(Rgb([r, g, b])),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AsciiConfig {
     pub width: u32,
     pub height: u32,
+    /// 字符单元格的宽高比，用于自动计算高度
+    pub cell_aspect: f32,
+    /// 在 width x height 的范围内按图像比例缩放
+    pub fit: bool,
+    pub filter: ResampleFilter,
     pub gamma: f32,
+    pub luminance_model: LuminanceModel,
+    /// 伽马校正前对亮度分布的调整
+    pub tone_mapping: ToneMapping,
     pub charset: Charset,
     pub custom_charset: String,
+    /// 自定义字符集已按从稀疏到密集排列：保持给定顺序并均匀分配亮度，不按测得的密度重新排序
+    pub presorted: bool,
     pub color: bool,
+    pub color_mode: ColorMode,
+    pub color_depth: ColorDepth,
+    pub color_tolerance: u8,
     pub invert: bool,
+    pub mode: RenderMode,
+    pub dither: DitherMode,
+    pub edge_detection: bool,
+    pub edge_threshold: f32,
+    pub threshold: u8,
+    pub alpha_mode: AlphaMode,
+    /// 测量字符密度和渲染图像时使用的字体链
+    pub font: FontChain,
 }
 
 impl Default for AsciiConfig {
@@ -55,113 +212,519 @@ impl Default for AsciiConfig {
         AsciiConfig {
             width: 80,
             height: 0,
+            cell_aspect: default_cell_aspect_ratio(),
+            fit: false,
+            filter: ResampleFilter::Box,
             gamma: 1.0,
+            luminance_model: LuminanceModel::default(),
+            tone_mapping: ToneMapping::default(),
             charset: Charset::DEFAULT,
             custom_charset: String::new(),
+            presorted: false,
             color: false,
+            color_mode: ColorMode::Foreground,
+            color_depth: ColorDepth::TrueColor,
+            color_tolerance: 0,
             invert: false,
+            mode: RenderMode::Density,
+            dither: DitherMode::None,
+            edge_detection: false,
+            edge_threshold: 0.1,
+            threshold: 128,
+            alpha_mode: AlphaMode::default(),
+            font: FontChain::default(),
         }
     }
 }
 
 pub struct AsciiMapper {
     config: AsciiConfig,
+    // 字符集中各字符的亮度，首次转换时测量，之后的转换（如 GIF 的每一帧）直接复用
+    glyph_levels: OnceLock<Vec<(char, f32)>>,
+    // 结构模式下的字形采样结果，同样只在首次使用时生成
+    glyph_matcher: OnceLock<GlyphMatcher>,
 }
 
 impl AsciiMapper {
     pub fn new(config: AsciiConfig) -> Self {
-        AsciiMapper { config }
+        AsciiMapper { config, glyph_levels: OnceLock::new(), glyph_matcher: OnceLock::new() }
     }
 
-    pub fn image_to_ascii(&self, img: &image::DynamicImage) -> Result<String, Box<dyn Error>> {
-        let mut ascii_art = String::new();
+    pub fn config(&self) -> &AsciiConfig {
+        &self.config
+    }
+
+    pub fn image_to_ascii(&self, img: &image::DynamicImage) -> Result<String> {
+        Ok(self.image_to_grid(img)?.to_ansi_text(self.config.color_depth, self.config.color_tolerance))
+    }
+
+    pub fn image_to_grid(&self, img: &image::DynamicImage) -> Result<AsciiGrid> {
+        if self.config.charset == Charset::CUSTOM && self.config.custom_charset.is_empty() {
+            return Err(AsciiArtError::InvalidCharset("自定义字符集为空".to_string()));
+        }
+
+        let (width, height) = self.output_dimensions(img);
+
+        if self.config.mode.is_block() {
+            return Ok(self.image_to_block_grid(img, width, height));
+        }
+
+        if self.config.charset == Charset::BRAILLE {
+            return Ok(self.image_to_braille_grid(img, width, height));
+        }
+
+        // 第一遍：将图像缩放到网格分辨率，得到每个单元格的平均颜色和亮度
+        let resampled = resampling::resample(img, width, height, self.config.filter);
+        let colors: Vec<Option<Rgb<u8>>> = resampled.pixels().map(|p| self.flatten_alpha(*p)).collect();
+        let luminances: Vec<f32> = self
+            .luminance_map(&colors, width, height)
+            .into_iter()
+            .map(|l| l as f32)
+            .collect();
 
-        let width = self.config.width;
-        let height = if self.config.height == 0 {
-            self.dynamic_height(img)
+        // 第二遍：量化亮度（可选抖动）并映射到字符
+        let glyph_levels = self.glyph_levels();
+        let level_values: Vec<f32> = glyph_levels.iter().map(|&(_, v)| v).collect();
+        let indices = dithering::quantize_to_levels(&luminances, width as usize, height as usize, &level_values, self.config.dither);
+
+        // 边缘模式：梯度足够强的单元格使用方向字符
+        let edges = if self.config.edge_detection {
+            edge_detection::cell_edges(&self.flatten_image(img), width, height)
         } else {
-            self.config.height
+            Vec::new()
         };
-        let gamma = self.config.gamma;
 
-        let width_ratio = img.width() as f32 / width as f32;
-        let height_ratio = img.height() as f32 / height as f32;
+        // 结构模式：按单元格内的亮度分布匹配字符形状
+        let structural = if self.config.mode == RenderMode::Structural {
+            Some((self.glyph_matcher()?, self.sub_cell_luminance(img, width, height)))
+        } else {
+            None
+        };
+
+        let mut grid = AsciiGrid::new(width, height);
+        for (i, &index) in indices.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let alpha = resampled.get_pixel(x, y)[3];
+            // 已被左侧宽字符占用的列
+            if grid.get(x, y).is_some_and(|c| c.is_continuation()) {
+                continue;
+            }
+
+            let Some(color) = colors[i] else {
+                // 完全透明的单元格保持空白
+                grid.set(x, y, AsciiCell { alpha: 0, ..AsciiCell::default() });
+                continue;
+            };
+
+            let glyph = match (edges.get(i), &structural) {
+                (Some(edge), _) if edge.magnitude >= self.config.edge_threshold => edge.glyph(),
+                (_, Some((matcher, patterns))) => {
+                    let size = MATRIX_COLS * MATRIX_ROWS;
+                    matcher.best_match(&patterns[i * size..(i + 1) * size])
+                }
+                _ => glyph_levels[index].0,
+            };
+
+            // 宽字符放不下时改用亮度最接近的窄字符
+            let glyph = if x + 1 == width && glyph.width().unwrap_or(1) > 1 {
+                Self::narrow_fallback(glyph_levels, glyph)
+            } else {
+                glyph
+            };
 
+            let (foreground, background) = self.cell_colors(Some(color));
+
+            grid.set(x, y, AsciiCell {
+                glyph,
+                foreground,
+                background,
+                luminance: luminances[i] as u8,
+                alpha: self.cell_alpha(alpha),
+                ..AsciiCell::default()
+            });
+        }
+
+        Ok(grid)
+    }
+
+    // 块元素模式：每个单元格按子像素亮度分为亮、暗两组，分别作为前景色和背景色
+    fn image_to_block_grid(&self, img: &image::DynamicImage, width: u32, height: u32) -> AsciiGrid {
+        let (cols, rows) = self.config.mode.cell_resolution();
+        let (sub_w, sub_h) = (width * cols, height * rows);
+        let sampled = resampling::resample(img, sub_w, sub_h, self.config.filter);
+        let colors: Vec<Option<Rgb<u8>>> = sampled.pixels().map(|p| self.flatten_alpha(*p)).collect();
+        let luminances = self.luminance_map(&colors, sub_w, sub_h);
+
+        let mut grid = AsciiGrid::new(width, height);
         for y in 0..height {
             for x in 0..width {
-                let base_x = (x as f32 * width_ratio) as u32;
-                let base_y = (y as f32 * height_ratio) as u32;
-
-                let mut total_r = 0;
-                let mut total_g = 0;
-                let mut total_b = 0;
-
-                for yy in 0..height_ratio as u32 {
-                    for xx in 0..width_ratio as u32 {
-                        let pixel = img.get_pixel(base_x + xx, base_y +yy);
-                        let rgb_channel = pixel.channels();
-                        total_r += rgb_channel[0] as u32;
-                        total_g += rgb_channel[1] as u32;
-                        total_b += rgb_channel[2] as u32;
+                let mut samples = Vec::with_capacity((cols * rows) as usize);
+                let mut total_alpha = 0u32;
+                for sy in 0..rows {
+                    for sx in 0..cols {
+                        let i = ((y * rows + sy) * sub_w + x * cols + sx) as usize;
+                        total_alpha += sampled.get_pixel(x * cols + sx, y * rows + sy)[3] as u32;
+                        // 完全透明的子像素不参与分组
+                        samples.push((colors[i], luminances[i]));
                     }
                 }
 
-                let pixel_count = width_ratio as u32 * height_ratio as u32;
-                let avg_r = total_r / pixel_count;
-                let avg_g = total_g / pixel_count;
-                let avg_b = total_b / pixel_count;
+                if samples.iter().all(|s| s.0.is_none()) {
+                    grid.set(x, y, AsciiCell { alpha: 0, ..AsciiCell::default() });
+                    continue;
+                }
 
-                let mut luminance = Self::rgb_to_luminance(avg_r, avg_g, avg_b);
-                luminance = Self::apply_gamma_correction(luminance, gamma);
+                // 彩色模式下以最亮和最暗子像素的中点为阈值，单色模式下使用固定阈值
+                let threshold = if self.config.color {
+                    let opaque = || samples.iter().filter(|s| s.0.is_some()).map(|s| s.1);
+                    let min = opaque().min().unwrap_or(0);
+                    let max = opaque().max().unwrap_or(0);
+                    (min + max).div_ceil(2)
+                } else {
+                    self.config.threshold as u32
+                };
 
-                let ascii_char = self.luminance_to_ascii(luminance);
+                let mut mask = 0u8;
+                for (i, &(color, luminance)) in samples.iter().enumerate() {
+                    if color.is_some() && (luminance >= threshold) != self.config.invert {
+                        mask |= 1 << i;
+                    }
+                }
 
-                if self.config.color {
-                    // 添加 ANSI 转义序列实现彩色输出
-                    let color_code = format!("\x1B[38;2;{};{};{}m", avg_r, avg_g, avg_b);
-                    ascii_art.push_str(&color_code);
-                    ascii_art.push(ascii_char);
-                    ascii_art.push_str(ANSI_RESET);
+                let (foreground, background) = if self.config.color {
+                    let on = Self::average_color(samples.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).filter_map(|(_, s)| s.0));
+                    let off = Self::average_color(samples.iter().enumerate().filter(|(i, _)| mask & (1 << i) == 0).filter_map(|(_, s)| s.0));
+                    // 所有子像素同属一组时背景与前景同色，透明的暗组不设背景色
+                    let all_opaque = samples.iter().all(|s| s.0.is_some());
+                    (on, off.or(if all_opaque { on } else { None }))
                 } else {
-                    ascii_art.push(ascii_char);
+                    (None, None)
+                };
+
+                let luminance = samples.iter().map(|s| s.1).sum::<u32>() / samples.len() as u32;
+
+                grid.set(x, y, AsciiCell {
+                    glyph: self.config.mode.block_glyph(mask),
+                    foreground,
+                    background,
+                    luminance: luminance as u8,
+                    alpha: self.cell_alpha((total_alpha / samples.len() as u32) as u8),
+                    ..AsciiCell::default()
+                });
+            }
+        }
+
+        grid
+    }
+
+    // 盲文模式：每个单元格表示 2x4 个经阈值化或抖动处理的像素
+    fn image_to_braille_grid(&self, img: &image::DynamicImage, width: u32, height: u32) -> AsciiGrid {
+        let (cols, rows) = self.cell_resolution();
+        let (sub_w, sub_h) = (width * cols, height * rows);
+        let sampled = resampling::resample(img, sub_w, sub_h, self.config.filter);
+
+        let colors: Vec<Option<Rgb<u8>>> = sampled.pixels().map(|p| self.flatten_alpha(*p)).collect();
+        let luminances: Vec<f32> = self
+            .luminance_map(&colors, sub_w, sub_h)
+            .into_iter()
+            .map(|l| l as f32)
+            .collect();
+
+        let dots: Vec<bool> = if self.config.dither == DitherMode::None {
+            luminances.iter().map(|&l| l >= self.config.threshold as f32).collect()
+        } else {
+            // 点的有无分别对应全暗和全亮
+            dithering::quantize_to_levels(&luminances, sub_w as usize, sub_h as usize, &[0.0, 255.0], self.config.dither)
+                .into_iter()
+                .map(|level| level == 1)
+                .collect()
+        };
+
+        let mut grid = AsciiGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut mask = 0u8;
+                let mut total_luminance = 0.0;
+                let mut total_alpha = 0u32;
+                let mut pixels = Vec::with_capacity((cols * rows) as usize);
+                for sy in 0..rows {
+                    for sx in 0..cols {
+                        let i = ((y * rows + sy) * sub_w + x * cols + sx) as usize;
+                        // 完全透明的像素不显示点
+                        if let Some(color) = colors[i] {
+                            if dots[i] != self.config.invert {
+                                mask |= 1 << (sy * cols + sx);
+                            }
+                            pixels.push(color);
+                        }
+                        total_luminance += luminances[i];
+                        total_alpha += sampled.get_pixel(x * cols + sx, y * rows + sy)[3] as u32;
+                    }
                 }
+
+                if pixels.is_empty() {
+                    grid.set(x, y, AsciiCell { alpha: 0, ..AsciiCell::default() });
+                    continue;
+                }
+
+                let (foreground, background) = self.cell_colors(Self::average_color(pixels.into_iter()));
+
+                grid.set(x, y, AsciiCell {
+                    glyph: block_elements::braille(mask),
+                    foreground,
+                    background,
+                    luminance: (total_luminance / (cols * rows) as f32) as u8,
+                    alpha: self.cell_alpha((total_alpha / (cols * rows)) as u8),
+                    ..AsciiCell::default()
+                });
             }
-            ascii_art.push('\n')
         }
 
-        Ok(ascii_art)
+        grid
+    }
+
+    // 按透明度处理方式得到像素的颜色，完全透明且保留透明度时返回 None
+    fn flatten_alpha(&self, pixel: Rgba<u8>) -> Option<Rgb<u8>> {
+        match self.config.alpha_mode {
+            AlphaMode::Matte(matte) => Some(Self::composite(pixel, matte)),
+            AlphaMode::Transparent if pixel[3] == 0 => None,
+            AlphaMode::Transparent => Some(Rgb([pixel[0], pixel[1], pixel[2]])),
+        }
+    }
+
+    // 按不透明度将像素叠加到底色上
+    fn composite(pixel: Rgba<u8>, matte: Rgb<u8>) -> Rgb<u8> {
+        let alpha = pixel[3] as f32 / 255.0;
+        Rgb([0, 1, 2].map(|c| (pixel[c] as f32 * alpha + matte[c] as f32 * (1.0 - alpha)).round() as u8))
+    }
+
+    // 单元格的不透明度，与底色混合后所有单元格均不透明
+    fn cell_alpha(&self, alpha: u8) -> u8 {
+        match self.config.alpha_mode {
+            AlphaMode::Matte(_) => 255,
+            AlphaMode::Transparent => alpha,
+        }
+    }
+
+    // 用于边缘检测的不透明图像，透明区域按底色（保留透明度时为黑色）填充
+    fn flatten_image(&self, img: &DynamicImage) -> DynamicImage {
+        if !img.color().has_alpha() {
+            return img.clone();
+        }
+
+        let rgba = img.to_rgba8();
+        let matte = match self.config.alpha_mode {
+            AlphaMode::Matte(matte) => matte,
+            AlphaMode::Transparent => Rgb([0, 0, 0]),
+        };
+        let flattened = image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            Self::composite(*rgba.get_pixel(x, y), matte)
+        });
+        DynamicImage::ImageRgb8(flattened)
     }
 
-    fn rgb_to_luminance(r: u32, g: u32, b: u32) -> u32 {
-        (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) as u32
+    // 按亮度模型计算亮度图 (0-255)，经色调调整后再进行伽马校正，完全透明的像素亮度为 0
+    fn luminance_map(&self, colors: &[Option<Rgb<u8>>], width: u32, height: u32) -> Vec<u32> {
+        let mut luminances: Vec<Option<u32>> = colors
+            .iter()
+            .map(|c| c.map(|c| self.config.luminance_model.luminance(c)))
+            .collect();
+        self.config.tone_mapping.apply(&mut luminances, width, height);
+
+        luminances
+            .into_iter()
+            .map(|l| l.map_or(0, |l| Self::apply_gamma_correction(l, self.config.gamma).min(255)))
+            .collect()
+    }
+
+    // 按颜色模式将单元格平均颜色分配为前景色和背景色
+    fn cell_colors(&self, color: Option<Rgb<u8>>) -> (Option<Rgb<u8>>, Option<Rgb<u8>>) {
+        if !self.config.color {
+            return (None, None);
+        }
+
+        match self.config.color_mode {
+            ColorMode::Foreground => (color, None),
+            ColorMode::Background => (None, color),
+            ColorMode::Combined => (color.map(Self::contrasting_color), color),
+        }
+    }
+
+    // 同色调的对比色：亮色向黑色混合，暗色向白色混合
+    fn contrasting_color(color: Rgb<u8>) -> Rgb<u8> {
+        let luminance = LuminanceModel::Rec709.luminance(color);
+        let target = if luminance >= 128 { 0.0 } else { 255.0 };
+        Rgb(color.0.map(|c| (c as f32 + (target - c as f32) * 0.6) as u8))
+    }
+
+    // 在线性光空间中求平均颜色
+    fn average_color(colors: impl Iterator<Item = Rgb<u8>>) -> Option<Rgb<u8>> {
+        let (mut total, mut count) = ([0.0f32; 3], 0u32);
+        for c in colors {
+            for (t, &v) in total.iter_mut().zip(c.0.iter()) {
+                *t += luminance::srgb_u8_to_linear(v);
+            }
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        Some(Rgb(total.map(|t| luminance::linear_to_srgb_u8(t / count as f32))))
+    }
+
+    // 按 MATRIX_COLS x MATRIX_ROWS 对每个单元格采样亮度 (0.0-1.0)，结果按单元格顺序连续存储
+    fn sub_cell_luminance(&self, img: &image::DynamicImage, width: u32, height: u32) -> Vec<f32> {
+        let cols = MATRIX_COLS as u32;
+        let rows = MATRIX_ROWS as u32;
+        let (sub_w, sub_h) = (width * cols, height * rows);
+        let sampled = resampling::resample(img, sub_w, sub_h, self.config.filter);
+        let colors: Vec<Option<Rgb<u8>>> = sampled.pixels().map(|p| self.flatten_alpha(*p)).collect();
+        // 完全透明的子像素视为黑色
+        let luminances = self.luminance_map(&colors, sub_w, sub_h);
+
+        let mut patterns = Vec::with_capacity((width * height * cols * rows) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                for sy in 0..rows {
+                    for sx in 0..cols {
+                        let value = luminances[((y * rows + sy) * sub_w + x * cols + sx) as usize] as f32 / 255.0;
+                        patterns.push(if self.config.invert { 1.0 - value } else { value });
+                    }
+                }
+            }
+        }
+        patterns
     }
 
     fn apply_gamma_correction(luminance: u32, gamma: f32) -> u32 {
         ((luminance as f32 / 255.0).powf(gamma) * 255.0) as u32
     }
 
-    fn luminance_to_ascii(&self, luminance: u32) -> char {
-        let charset = if self.config.charset == Charset::CUSTOM {
+    fn charset_chars(&self) -> Vec<char> {
+        if self.config.charset == Charset::CUSTOM {
             if self.config.invert {
                 self.config.custom_charset.chars().rev().collect::<Vec<char>>()
             } else {
                 self.config.custom_charset.chars().collect::<Vec<char>>()
             }
+        } else if self.config.invert {
+            self.config.charset.as_str().chars().rev().collect::<Vec<char>>()
         } else {
-            if self.config.invert {
-                self.config.charset.as_str().chars().rev().collect::<Vec<char>>()
-            } else {
-                self.config.charset.as_str().chars().collect::<Vec<char>>()
-            }
-        };
+            self.config.charset.as_str().chars().collect::<Vec<char>>()
+        }
+    }
 
-        let index = (luminance as f32 * charset.len() as f32 / 255.0) as usize;
-        charset[index.min(charset.len() - 1)]
+    fn glyph_levels(&self) -> &[(char, f32)] {
+        self.glyph_levels.get_or_init(|| self.measure_glyph_levels(&self.charset_chars()))
+    }
+
+    fn glyph_matcher(&self) -> Result<&GlyphMatcher> {
+        if let Some(matcher) = self.glyph_matcher.get() {
+            return Ok(matcher);
+        }
+        let matcher = GlyphMatcher::new(&self.charset_chars(), &self.config.font)?;
+        Ok(self.glyph_matcher.get_or_init(|| matcher))
     }
 
-    fn dynamic_height(&self, image: &image::DynamicImage) -> u32 {
+    // 按字体链中测得的密度确定每个字符所代表的亮度 (0-255)，结果按亮度升序排列
+    // 最稀疏的字符对应 0，最密集的字符对应 255，反转时相反
+    fn measure_glyph_levels(&self, charset: &[char]) -> Vec<(char, f32)> {
+        let analyzer = CharsetAnalyzer::with_fonts(self.config.font.clone());
+
+        // 已排序的字符集，或字体中缺少字符而无法测量密度时，按字符集中的顺序均匀分布
+        let presorted = self.config.presorted && self.config.charset == Charset::CUSTOM;
+        if presorted || !charset.iter().all(|&c| analyzer.has_glyph(c)) {
+            let last = charset.len().saturating_sub(1).max(1) as f32;
+            return charset.iter().enumerate().map(|(i, &c)| (c, i as f32 / last * 255.0)).collect();
+        }
+
+        let densities: Vec<f32> = charset.iter().map(|&c| analyzer.density(c)).collect();
+        let min = densities.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = densities.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+        let mut levels: Vec<(char, f32)> = charset
+            .iter()
+            .zip(densities)
+            .map(|(&c, d)| {
+                let normalized = if max > min { (d - min) / (max - min) } else { 0.0 };
+                let level = if self.config.invert { 1.0 - normalized } else { normalized };
+                (c, level * 255.0)
+            })
+            .collect();
+        levels.sort_by(|a, b| a.1.total_cmp(&b.1));
+        levels
+    }
+
+    // 与给定字符亮度最接近的窄字符，字符集中没有窄字符时使用空格
+    fn narrow_fallback(glyph_levels: &[(char, f32)], glyph: char) -> char {
+        let level = glyph_levels.iter().find(|&&(c, _)| c == glyph).map_or(0.0, |&(_, v)| v);
+        glyph_levels
+            .iter()
+            .filter(|&&(c, _)| c.width().unwrap_or(1) == 1)
+            .min_by(|a, b| (a.1 - level).abs().total_cmp(&(b.1 - level).abs()))
+            .map_or(' ', |&(c, _)| c)
+    }
+
+    // 每个字符单元格包含的子像素列数和行数，盲文字符集固定为 2x4
+    fn cell_resolution(&self) -> (u32, u32) {
+        if self.config.charset == Charset::BRAILLE && !self.config.mode.is_block() {
+            (2, 4)
+        } else {
+            self.config.mode.cell_resolution()
+        }
+    }
+
+    fn dynamic_height(&self, image: &image::DynamicImage, width: u32) -> u32 {
+        // 按字符单元格的宽高比修正，使输出保持图像的实际比例
         let aspect_ratio = image.height() as f32 / image.width() as f32;
-        let calculated_height = (self.config.width as f32 * aspect_ratio) as u32;
-        calculated_height
+        ((width as f32 * aspect_ratio * self.config.cell_aspect).round() as u32).max(1)
+    }
+
+    // 计算输出的列数和行数
+    fn output_dimensions(&self, image: &image::DynamicImage) -> (u32, u32) {
+        let width = self.config.width.max(1);
+
+        if self.config.height == 0 {
+            return (width, self.dynamic_height(image, width));
+        }
+
+        if !self.config.fit {
+            return (width, self.config.height);
+        }
+
+        // fit 模式：宽度优先，超出高度限制时改为按高度缩放
+        let height = self.dynamic_height(image, width);
+        if height <= self.config.height {
+            (width, height)
+        } else {
+            let aspect_ratio = image.width() as f32 / image.height() as f32;
+            let fitted_width = (self.config.height as f32 * aspect_ratio / self.config.cell_aspect).round() as u32;
+            (fitted_width.clamp(1, width), self.config.height)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(custom_charset: &str, presorted: bool) -> Vec<(char, f32)> {
+        let config = AsciiConfig {
+            charset: Charset::CUSTOM,
+            custom_charset: custom_charset.to_string(),
+            presorted,
+            ..AsciiConfig::default()
+        };
+        AsciiMapper::new(config).glyph_levels().to_vec()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn presorted_charset_keeps_given_order() {
+        // 故意按从密集到稀疏给出
+        assert_eq!(levels("@+. ", true), vec![('@', 0.0), ('+', 85.0), ('.', 170.0), (' ', 255.0)]);
+
+        // 未排序时按测得的密度重新排列
+        let measured: String = levels("@+. ", false).iter().map(|&(c, _)| c).collect();
+        assert_eq!(measured, " .+@");
+    }
+}
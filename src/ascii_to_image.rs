@@ -1,165 +1,415 @@
-use crate::ascii_mapping::AsciiConfig;
-use image::{ImageBuffer, Rgb};
-use rusttype::{Font, Scale};
+use crate::ansi_parser;
+use crate::ascii_grid::{AsciiCell, AsciiGrid};
+use crate::error::{AsciiArtError, Result};
+use crate::font_chain::FontChain;
+use crate::luminance::{linear_to_srgb_u8, srgb_u8_to_linear};
+use image::{Rgb, Rgba, RgbaImage};
+use rusttype::Scale;
+use std::sync::OnceLock;
+
+/// 内嵌字体渲染时字符单元格的宽高比（字符前进宽度 / 单元格高度）
+pub fn default_cell_aspect_ratio() -> f32 {
+    static RATIO: OnceLock<f32> = OnceLock::new();
+    *RATIO.get_or_init(|| FontChain::embedded().cell_aspect_ratio())
+}
+
+/// 静态图像输出（PNG/JPEG）的默认字号
+pub const DEFAULT_IMAGE_FONT_SIZE: u32 = 32;
+/// GIF 输出的默认字号
+pub const DEFAULT_GIF_FONT_SIZE: u32 = 16;
+
+/// 超采样倍数的上限
+pub const MAX_SUPERSAMPLE: u32 = 8;
+
+/// 字形的栅格化方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rasterization {
+    /// 抗锯齿，字形边缘按覆盖率与背景混合
+    #[default]
+    Smooth,
+    /// 像素对齐的点阵风格，每个像素要么是前景色要么是背景色
+    Crisp,
+}
+
+impl std::str::FromStr for Rasterization {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "smooth" | "antialias" => Ok(Rasterization::Smooth),
+            "crisp" | "bitmap" => Ok(Rasterization::Crisp),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的栅格化方式: {s}"))),
+        }
+    }
+}
+
+/// 将字符网格渲染为图像（PNG、JPEG、GIF）时的设置
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageRenderOptions {
+    /// 字号（像素），为 None 时静态图像使用 [`DEFAULT_IMAGE_FONT_SIZE`]，GIF 使用 [`DEFAULT_GIF_FONT_SIZE`]
+    pub font_size: Option<u32>,
+    /// 字符单元格宽度（像素），为 None 时取主字体的字符前进宽度
+    pub cell_width: Option<u32>,
+    /// 行高（像素），为 None 时等于字号
+    pub line_height: Option<u32>,
+    /// 图像四周的留白（像素）
+    pub padding: u32,
+    pub background: Rgb<u8>,
+    pub foreground: Rgb<u8>,
+    /// 未设置背景色的单元格和留白保持透明（JPEG 不支持透明度，仍使用背景色）
+    pub transparent_background: bool,
+    pub rasterization: Rasterization,
+    /// 每个像素在每个方向上的采样数 (1-8)，1 表示不超采样
+    pub supersample: u32,
+}
+
+impl Default for ImageRenderOptions {
+    fn default() -> Self {
+        ImageRenderOptions {
+            font_size: None,
+            cell_width: None,
+            line_height: None,
+            padding: 0,
+            background: Rgb([0x0C, 0x0C, 0x0C]),
+            foreground: Rgb([0xCC, 0xCC, 0xCC]),
+            transparent_background: false,
+            rasterization: Rasterization::Smooth,
+            supersample: 1,
+        }
+    }
+}
 
 pub struct AsciiToImageRenderer {
-    config: AsciiConfig,
     font_size: u32,
-    font_data: Vec<u8>,
+    fonts: FontChain,
     background_color: Rgb<u8>,
     foreground_color: Rgb<u8>,
+    cell_width: Option<u32>,
+    line_height: Option<u32>,
+    padding: u32,
+    transparent_background: bool,
+    rasterization: Rasterization,
+    supersample: u32,
 }
 
 impl AsciiToImageRenderer {
-    pub fn new(config: AsciiConfig, font_size: u32) -> Result<Self, Box<dyn std::error::Error>> {
-        let font_data = include_bytes!("../res/DejaVuSansMono.ttf").to_vec();
+    pub fn new(font_size: u32) -> Result<Self> {
+        if font_size == 0 {
+            return Err(AsciiArtError::InvalidArgument("字号必须大于 0".to_string()));
+        }
 
         Ok(Self {
-            config,
             font_size,
-            font_data,
+            fonts: FontChain::embedded(),
             background_color: Rgb([0x0C, 0x0C, 0x0C]),
             foreground_color: Rgb([0xCC, 0xCC, 0xCC]),
+            cell_width: None,
+            line_height: None,
+            padding: 0,
+            transparent_background: false,
+            rasterization: Rasterization::Smooth,
+            supersample: 1,
+        })
+    }
+
+    /// 按渲染设置创建，设置中未指定字号时使用 `default_font_size`
+    pub fn from_options(options: &ImageRenderOptions, default_font_size: u32) -> Result<Self> {
+        let renderer = Self::new(options.font_size.unwrap_or(default_font_size))?
+            .with_colors(options.background, options.foreground);
+        Ok(Self {
+            cell_width: options.cell_width,
+            line_height: options.line_height,
+            padding: options.padding,
+            transparent_background: options.transparent_background,
+            rasterization: options.rasterization,
+            supersample: options.supersample.clamp(1, MAX_SUPERSAMPLE),
+            ..renderer
         })
     }
-    
+
     pub fn with_colors(mut self, background: Rgb<u8>, foreground: Rgb<u8>) -> Self {
         self.background_color = background;
         self.foreground_color = foreground;
         self
     }
 
-    // 辅助函数：从ANSI转义序列中提取RGB颜色
-    fn parse_ansi_color(ansi_sequence: &str) -> Option<Rgb<u8>> {
-        let parts: Vec<&str> = ansi_sequence.split(';').collect();
-        if parts.len() == 5 && parts[0] == "\x1B[38" && parts[1] == "2" {
-            if let (Some(r), Some(g), Some(b)) = (
-                parts[2].parse::<u8>().ok(),
-                parts[3].parse::<u8>().ok(),
-                parts[4].strip_suffix('m').and_then(|s| s.parse::<u8>().ok()),
-            ) {
-                return Some(Rgb([r, g, b]));
-            }
-        }
-        None
+    /// 使用给定的字体链渲染，主字体中缺少的字符从后备字体中查找
+    pub fn with_fonts(mut self, fonts: FontChain) -> Self {
+        self.fonts = fonts;
+        self
     }
 
-    // 检查是否是重置颜色的ANSI序列([0m)
-    fn is_reset_sequence(seq: &str) -> bool {
-        seq == "\x1B[0m"
+    /// 字符单元格的宽度和高度（像素）
+    pub fn cell_size(&self) -> (u32, u32) {
+        let width = self.cell_width.unwrap_or_else(|| {
+            let scale = Scale::uniform(self.font_size as f32);
+            let advance = self.fonts.primary().glyph('M').scaled(scale).h_metrics().advance_width;
+            advance.round().max(1.0) as u32
+        });
+        (width.max(1), self.line_height.unwrap_or(self.font_size).max(1))
     }
 
-    pub fn render_ascii_to_image(&mut self, ascii_art: &str) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, Box<dyn std::error::Error>> {
-        let lines: Vec<&str> = ascii_art.lines().collect();
-        let char_width = (self.font_size as f32 * 0.6) as u32;
-        let width = self.config.width * char_width;
-        let height = lines.len() as u32 * self.font_size;
-
-        // 创建带有指定背景色的图像
-        let mut img = ImageBuffer::from_pixel(width, height, self.background_color);
-
-        // 加载字体
-        let font = Font::try_from_vec(self.font_data.clone())
-            .ok_or("Failed to load font")?;
-        
-        let scale = Scale {
-            x: self.font_size as f32,
-            y: self.font_size as f32,
-        };
+    /// 将带 ANSI 转义序列的文本渲染为图像，支持 SGR 颜色和文本属性
+    pub fn render_ascii_to_image(&self, ascii_art: &str) -> Result<RgbaImage> {
+        let grid = ansi_parser::parse_ansi_text(ascii_art, self.foreground_color, self.background_color);
+        self.render_grid_to_image(&grid)
+    }
 
-        // 获取字体的垂直度量信息
-        let v_metrics = font.v_metrics(scale);
-        let ascent = v_metrics.ascent;
-        let descent = v_metrics.descent;
-        let font_height = (ascent - descent).ceil() as u32;
+    /// 将字符网格渲染为图像，单元格的不透明度决定对应区域背景的透明度
+    pub fn render_grid_to_image(&self, grid: &AsciiGrid) -> Result<RgbaImage> {
+        let (char_width, line_height) = self.cell_size();
+        let width = grid.width() * char_width + 2 * self.padding;
+        let height = grid.height() * line_height + 2 * self.padding;
 
-        // 计算字符单元格的中心偏移量
-        let cell_center_offset_y = self.font_size / 2;
-        // 基于字体基线的偏移量
-        let baseline_offset = (ascent - (font_height as f32 / 2.0)).ceil() as u32;
+        // 创建带有指定背景色的图像
+        let [r, g, b] = self.background_color.0;
+        let base_alpha = if self.transparent_background { 0 } else { 255 };
+        let mut img = RgbaImage::from_pixel(width, height, Rgba([r, g, b, base_alpha]));
 
         // 渲染每个字符
-        for (y, line) in lines.iter().enumerate() {
-            let mut current_x = 0;
-            let mut chars = line.chars().peekable();
-
-            while let Some(c) = chars.next() {
-                if c == '\x1B' { // 检查ANSI转义序列
-                    let mut seq = String::from('\x1B');
-                    while let Some(&next_c) = chars.peek() {
-                        seq.push(next_c);
-                        chars.next();
-                        if next_c == 'm' {
-                            break;
-                        }
-                    }
+        for (y, row) in grid.rows().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                // 宽字符的右半列已随左半列一起绘制
+                if cell.is_continuation() {
+                    continue;
+                }
 
-                    // 检查是否是重置序列，如果是则忽略它
-                    if Self::is_reset_sequence(&seq) {
-                        continue;
-                    }
+                let background_color = cell.background.unwrap_or(self.background_color);
+                let foreground_color = cell.foreground.unwrap_or(self.foreground_color);
+                // 未设置背景色的单元格在透明背景下只保留字形
+                let cell_alpha = if cell.background.is_none() && self.transparent_background { 0 } else { cell.alpha };
+                let background_alpha = cell_alpha as f32 / 255.0;
 
-                    // 处理颜色序列
-                    if let Some(color) = Self::parse_ansi_color(&seq) {
-                        self.foreground_color = color;
-                        continue;
+                // 计算字符在图像中的位置，宽字符占用两列
+                let base_x = self.padding + x as u32 * char_width;
+                let base_y = self.padding + y as u32 * line_height;
+                let cell_width = (char_width * cell.display_width()).min(width - self.padding - base_x);
+
+                // 填充单元格背景色，透明单元格的背景按其不透明度保留透明
+                if cell.background.is_some() || cell_alpha < base_alpha {
+                    let [r, g, b] = background_color.0;
+                    for py in base_y..base_y + line_height {
+                        for px in base_x..base_x + cell_width {
+                            img.put_pixel(px, py, Rgba([r, g, b, cell_alpha]));
+                        }
                     }
                 }
 
-                if c == ' ' {
-                    current_x += 1;
+                if cell.glyph == ' ' && !cell.style.underline {
                     continue;
                 }
 
-                // 计算字符在图像中的位置
-                let base_x = current_x as u32 * char_width;
-                let base_y = y as u32 * self.font_size + cell_center_offset_y;
-
-                // 获取字符的字形
-                let glyph = font.glyph(c).scaled(scale);
-                if glyph.id() != rusttype::GlyphId(0) {
-                    // 计算字形的像素位置，基于基线对齐
-                    let glyph_pos = glyph.positioned(rusttype::point(0.0, ascent));
-
-                    // 获取字形的像素覆盖范围
-                    if let Some(bb) = glyph_pos.pixel_bounding_box() {
-                        // 计算字符的宽度和水平偏移
-                        let glyph_width = bb.width() as u32;
-
-                        // 安全计算水平偏移量
-                        let h_offset = if glyph_width > char_width {
-                            0 // 如果字形宽度大于单元格宽度，不应用额外偏移
-                        } else {
-                            ((char_width - glyph_width) / 2).saturating_sub(bb.min.x as u32)
-                        };
-
-                        // 渲染字形到图像
-                        glyph_pos.draw(|gx, gy, v| {
-                            // 修正后的坐标计算，添加水平偏移
-                            let img_x = base_x + h_offset + gx;
-                            let img_y = base_y - baseline_offset + gy;
-
-                            // 确保在图像边界内
-                            if img_x < width && img_y < height {
-                                // 根据字符的透明度混合前景色和背景色
-                                let alpha = v;
-                                let r = ((self.foreground_color[0] as f32 * alpha) +
-                                    (self.background_color[0] as f32 * (1.0 - alpha))) as u8;
-                                let g = ((self.foreground_color[1] as f32 * alpha) +
-                                    (self.background_color[1] as f32 * (1.0 - alpha))) as u8;
-                                let b = ((self.foreground_color[2] as f32 * alpha) +
-                                    (self.background_color[2] as f32 * (1.0 - alpha))) as u8;
-
-                                img.put_pixel(img_x, img_y, Rgb([r, g, b]));
-                            }
-                        });
+                // 按字形覆盖率（以及粗体、斜体、下划线）计算单元格内每个像素的覆盖率
+                let coverage = self.cell_coverage(cell, cell_width, line_height);
+
+                // 将字形按覆盖率叠加到（可能半透明的）单元格背景上
+                for (i, &v) in coverage.iter().enumerate().filter(|&(_, &v)| v > 0.0) {
+                    let (px, py) = (base_x + i as u32 % cell_width, base_y + i as u32 / cell_width);
+                    img.put_pixel(px, py, blend(foreground_color, background_color, background_alpha, v));
+                }
+            }
+        }
+
+        Ok(img)
+    }
+
+    // 单元格内（按行存储）每个像素的字形覆盖率 (0.0-1.0)，超出单元格的部分被裁剪
+    fn cell_coverage(&self, cell: &AsciiCell, cell_width: u32, line_height: u32) -> Vec<f32> {
+        // 超采样时按放大后的字号和单元格绘制，再按块平均缩小
+        let factor = self.supersample;
+        let (w, h) = ((cell_width * factor) as i32, (line_height * factor) as i32);
+        let size = (self.font_size * factor) as f32;
+        let scale = Scale::uniform(size);
+
+        // 字形的行高 (ascent - descent) 在单元格中垂直居中，baseline 为基线到单元格顶部的距离
+        let v_metrics = self.fonts.primary().v_metrics(scale);
+        let baseline = ((h as f32 - (v_metrics.ascent - v_metrics.descent)) / 2.0 + v_metrics.ascent).round() as i32;
+
+        let mut samples = vec![0.0f32; (w * h) as usize];
+        let mut cover = |x: i32, y: i32, v: f32| {
+            if x >= 0 && y >= 0 && x < w && y < h {
+                let sample = &mut samples[(y * w + x) as usize];
+                *sample = sample.max(v);
+            }
+        };
+
+        // 从字体链中第一个包含该字符的字体获取字形
+        if let Some(font) = self.fonts.font_for(cell.glyph) {
+            let glyph = font.glyph(cell.glyph).scaled(scale).positioned(rusttype::point(0.0, 0.0));
+            // 字形在单元格中水平居中、按基线对齐
+            if let Some(bb) = glyph.pixel_bounding_box() {
+                let origin_x = (w - bb.width()) / 2;
+                let origin_y = baseline + bb.min.y;
+                // 粗体向右加粗约 1/16 字号，斜体按 12 度左右向右倾斜
+                let embolden = if cell.style.bold { (size / 16.0).max(1.0) as i32 } else { 0 };
+                let slant = if cell.style.italic { 0.2 } else { 0.0 };
+
+                glyph.draw(|gx, gy, v| {
+                    let y = origin_y + gy as i32;
+                    let x = origin_x + gx as i32 + ((baseline - y) as f32 * slant).round() as i32;
+                    for dx in 0..=embolden {
+                        cover(x + dx, y, v);
                     }
+                });
+            }
+        }
+
+        // 下划线位于基线与字体下缘之间
+        if cell.style.underline {
+            let thickness = (size / 16.0).max(1.0) as i32;
+            let top = baseline + (-v_metrics.descent / 2.0).round() as i32 - thickness / 2;
+            for y in top..top + thickness {
+                for x in 0..w {
+                    cover(x, y, 1.0);
                 }
+            }
+        }
+
+        let (cell_width, factor) = (cell_width as usize, factor as usize);
+        let mut coverage = vec![0.0f32; cell_width * line_height as usize];
+        for (i, pixel) in coverage.iter_mut().enumerate() {
+            let (x, y) = (i % cell_width * factor, i / cell_width * factor);
+            let total: f32 = (y..y + factor)
+                .flat_map(|sy| samples[sy * w as usize + x..sy * w as usize + x + factor].iter())
+                .sum();
+            let v = total / (factor * factor) as f32;
+
+            *pixel = match self.rasterization {
+                Rasterization::Smooth => v,
+                Rasterization::Crisp => if v >= 0.5 { 1.0 } else { 0.0 },
+            };
+        }
+
+        coverage
+    }
+}
+
+// 以覆盖率 `coverage` 将前景色叠加到不透明度为 `background_alpha` 的背景色上，混合在线性光空间中进行
+fn blend(foreground: Rgb<u8>, background: Rgb<u8>, background_alpha: f32, coverage: f32) -> Rgba<u8> {
+    let background_weight = background_alpha * (1.0 - coverage);
+    let alpha = coverage + background_weight;
+    let channel = |c: usize| {
+        let value = srgb_u8_to_linear(foreground[c]) * coverage + srgb_u8_to_linear(background[c]) * background_weight;
+        if alpha > 0.0 { linear_to_srgb_u8(value / alpha) } else { 0 }
+    };
+    Rgba([channel(0), channel(1), channel(2), (alpha * 255.0).round() as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ascii_grid::{AsciiCell, TextStyle};
 
-                current_x += 1;
+    // 一行混合了带样式的窄字符和宽字符，另一行包含半透明和带背景色的单元格
+    fn styled_grid() -> AsciiGrid {
+        let styles = [
+            TextStyle { bold: true, ..TextStyle::default() },
+            TextStyle { italic: true, ..TextStyle::default() },
+            TextStyle { underline: true, ..TextStyle::default() },
+            TextStyle { bold: true, italic: true, underline: true },
+        ];
+
+        let mut grid = AsciiGrid::new(9, 2);
+        let mut x = 0;
+        for (i, glyph) in ['W', '漢', 'g', '字', '█'].into_iter().enumerate() {
+            let cell = AsciiCell { style: styles[i % styles.len()], ..AsciiCell::new(glyph) };
+            grid.set(x, 0, cell);
+            x += cell.display_width();
+        }
+        // 最后一列放不下的宽字符被截断在图像范围内
+        grid.set(8, 1, AsciiCell { style: styles[3], ..AsciiCell::new('字') });
+        grid.set(0, 1, AsciiCell { alpha: 100, ..AsciiCell::new('@') });
+        grid.set(1, 1, AsciiCell { background: Some(Rgb([0, 0, 255])), ..AsciiCell::new('j') });
+        grid
+    }
+
+    // 单元格范围内是否有不同于背景色的像素
+    fn has_ink(img: &RgbaImage, options: &ImageRenderOptions, x: u32, y: u32, width: u32, height: u32) -> bool {
+        let [r, g, b] = options.background.0;
+        (y..y + height).any(|py| (x..x + width).any(|px| *img.get_pixel(px, py) != Rgba([r, g, b, 255])))
+    }
+
+    #[test]
+    fn renders_styled_wide_glyphs_at_all_sizes() {
+        let grid = styled_grid();
+        for font_size in 6..=64 {
+            for rasterization in [Rasterization::Smooth, Rasterization::Crisp] {
+                for supersample in [1, MAX_SUPERSAMPLE] {
+                    let options = ImageRenderOptions {
+                        font_size: Some(font_size),
+                        padding: 3,
+                        rasterization,
+                        supersample,
+                        ..ImageRenderOptions::default()
+                    };
+                    let renderer = AsciiToImageRenderer::from_options(&options, DEFAULT_IMAGE_FONT_SIZE).unwrap();
+                    let (cell_width, line_height) = renderer.cell_size();
+                    let img = renderer.render_grid_to_image(&grid).unwrap();
+                    assert_eq!(
+                        img.dimensions(),
+                        (9 * cell_width + 6, 2 * line_height + 6),
+                        "font size {font_size}, {rasterization:?}, supersample {supersample}"
+                    );
+
+                    // 小字号下字形仍应落在自己的单元格内
+                    if font_size <= 8 {
+                        for (column, row) in [(0, 0), (3, 0), (6, 0), (0, 1)] {
+                            assert!(
+                                has_ink(&img, &options, 3 + column * cell_width, 3 + row * line_height, cell_width, line_height),
+                                "no ink in cell ({column}, {row}) at font size {font_size}, {rasterization:?}, supersample {supersample}"
+                            );
+                        }
+                    }
+                }
             }
         }
+    }
+
+    #[test]
+    fn renders_with_cells_smaller_than_glyphs() {
+        let options = ImageRenderOptions {
+            font_size: Some(40),
+            cell_width: Some(3),
+            line_height: Some(4),
+            supersample: 2,
+            ..ImageRenderOptions::default()
+        };
+        let img = AsciiToImageRenderer::from_options(&options, DEFAULT_IMAGE_FONT_SIZE)
+            .unwrap()
+            .render_grid_to_image(&styled_grid())
+            .unwrap();
+        assert_eq!(img.dimensions(), (27, 8));
+    }
+
+    #[test]
+    fn crisp_rasterization_uses_only_two_colors() {
+        let mut grid = AsciiGrid::new(3, 1);
+        for (x, glyph) in "@g/".chars().enumerate() {
+            grid.set(x as u32, 0, AsciiCell::new(glyph));
+        }
+        let options = ImageRenderOptions { font_size: Some(12), rasterization: Rasterization::Crisp, ..ImageRenderOptions::default() };
+        let img = AsciiToImageRenderer::from_options(&options, DEFAULT_IMAGE_FONT_SIZE)
+            .unwrap()
+            .render_grid_to_image(&grid)
+            .unwrap();
 
-        Ok(img)
+        let [br, bg, bb] = options.background.0;
+        let [fr, fg, fb] = options.foreground.0;
+        assert!(img.pixels().all(|p| *p == Rgba([br, bg, bb, 255]) || *p == Rgba([fr, fg, fb, 255])));
+        assert!(img.pixels().any(|p| *p == Rgba([fr, fg, fb, 255])));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn half_coverage_blends_to_linear_midpoint() {
+        let pixel = blend(Rgb([255, 255, 255]), Rgb([0, 0, 0]), 1.0, 0.5);
+        let midpoint = linear_to_srgb_u8(0.5);
+        assert_eq!(pixel, Rgba([midpoint, midpoint, midpoint, 255]));
+        // sRGB 空间中的简单线性混合会得到 128
+        assert!(pixel[0] > 180);
+    }
+
+    #[test]
+    fn rejects_zero_font_size() {
+        assert!(AsciiToImageRenderer::new(0).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+// Unicode 块元素及盲文点阵字符表，掩码的第 i 位对应按行排列的第 i 个子像素
+
+/// 1x2 半块字符（上、下）
+pub const HALF_BLOCKS: [char; 4] = [' ', '▀', '▄', '█'];
+
+/// 2x2 象限字符（左上、右上、左下、右下）
+pub const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// 2x3 六分块字符（Symbols for Legacy Computing, U+1FB00 起）
+pub fn sextant(mask: u8) -> char {
+    match mask & 0x3F {
+        0 => ' ',
+        21 => '▌',
+        42 => '▐',
+        63 => '█',
+        m => {
+            // U+1FB00 区段跳过了与已有块字符重复的 4 种组合
+            let index = m as u32 - 1 - (m > 21) as u32 - (m > 42) as u32;
+            char::from_u32(0x1FB00 + index).unwrap_or('?')
+        }
+    }
+}
+
+// 2x4 子像素（按行排列）对应的盲文点位
+const BRAILLE_DOTS: [u8; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+/// 2x4 盲文点阵字符（U+2800 起）
+pub fn braille(mask: u8) -> char {
+    let dots = BRAILLE_DOTS
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| mask & (1 << i) != 0)
+        .fold(0u32, |acc, (_, &dot)| acc | dot as u32);
+    char::from_u32(0x2800 + dots).unwrap_or(' ')
+}
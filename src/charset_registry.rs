@@ -0,0 +1,118 @@
+use crate::ascii_mapping::Charset;
+use crate::config_file::load_config;
+use crate::error::{AsciiArtError, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// 一个具名字符集的定义
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CharsetDefinition {
+    pub name: String,
+    /// 字符集中的字符，按从稀疏到密集排列（`sorted` 为 false 时顺序任意）
+    pub glyphs: String,
+    /// 字符是否已按密度排列，为 false 时使用前按字体中测得的密度排序
+    #[serde(default)]
+    pub sorted: bool,
+    /// 推荐用于显示该字符集的字体文件，从文件加载时相对路径以定义文件所在目录为基准
+    #[serde(default)]
+    pub font: Option<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl CharsetDefinition {
+    fn builtin(name: &str, glyphs: &str, sorted: bool, description: &str) -> Self {
+        CharsetDefinition {
+            name: name.to_string(),
+            glyphs: glyphs.to_string(),
+            sorted,
+            font: None,
+            description: description.to_string(),
+        }
+    }
+}
+
+// 字符集定义文件的结构，每个 `[[charset]]` 表为一个字符集
+#[derive(Deserialize)]
+struct CharsetFile {
+    #[serde(default)]
+    charset: Vec<CharsetDefinition>,
+}
+
+/// 按名称查找字符集，包含内置字符集和从文件加载的字符集
+#[derive(Debug, Clone)]
+pub struct CharsetRegistry {
+    charsets: Vec<CharsetDefinition>,
+}
+
+impl Default for CharsetRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl CharsetRegistry {
+    /// 仅包含内置字符集的注册表
+    pub fn builtin() -> Self {
+        let enum_charsets = [
+            (Charset::DEFAULT, "default", "70-level ASCII ramp"),
+            (Charset::SIMPLE, "simple", "10-level ASCII ramp"),
+            (Charset::BLOCK, "block", "Block elements of increasing height"),
+            (Charset::PIXEL, "pixel", "Single full block"),
+            (Charset::BRAILLE, "braille", "Braille dots, 2x4 pixels per character"),
+        ];
+
+        let mut charsets: Vec<CharsetDefinition> = enum_charsets
+            .iter()
+            .map(|&(charset, name, description)| CharsetDefinition::builtin(name, charset.as_str(), true, description))
+            .collect();
+
+        charsets.extend([
+            CharsetDefinition::builtin(
+                "katakana",
+                " ･ｰｨｧｯｩｪｫｬｭｮｦﾉﾍﾞﾟｲｺｴﾆﾄﾘﾚｸｹﾀﾂﾃﾅﾊﾋﾌﾎﾏﾐﾑﾒﾓﾔﾕﾖﾗﾙﾛﾜﾝｱｳｵｶｷｻｼｽｾｿﾁﾈ",
+                true,
+                "Half-width katakana (needs a font with katakana)",
+            ),
+            CharsetDefinition::builtin("box", " ╴╶╵╷─│┄┆┈┊╌╎┌┐└┘├┤┬┴┼═║╔╗╚╝╠╣╦╩╬", false, "Box-drawing characters"),
+            CharsetDefinition::builtin("dots", " ·∙•◦○◌◎◉●", false, "Dots of increasing size"),
+            CharsetDefinition::builtin("emoji", "⬛🟫🟪🟥🟦🟩🟧🟨⬜", true, "Colored square emoji ordered by brightness (wide)"),
+            CharsetDefinition::builtin("cjk", "　丶一二三十工王田回国固園圏鬱", true, "Full-width CJK ideographs ordered by stroke density"),
+        ]);
+
+        CharsetRegistry { charsets }
+    }
+
+    /// 从 TOML 或 JSON 文件（按扩展名区分）加载字符集定义，与已有字符集同名时覆盖原定义
+    pub fn load_file(&mut self, path: &str) -> Result<()> {
+        let invalid = |e: &dyn std::fmt::Display| AsciiArtError::InvalidCharset(format!("无效的字符集定义文件 {path}: {e}"));
+        let file: CharsetFile = load_config(path, |e| invalid(&e))?;
+
+        let base_dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        for mut definition in file.charset {
+            if definition.glyphs.is_empty() {
+                return Err(invalid(&format!("字符集 {} 为空", definition.name)));
+            }
+            definition.font = definition.font.map(|font| base_dir.join(font).to_string_lossy().into_owned());
+            self.register(definition);
+        }
+        Ok(())
+    }
+
+    /// 添加字符集，与已有字符集同名时覆盖原定义
+    pub fn register(&mut self, definition: CharsetDefinition) {
+        match self.charsets.iter_mut().find(|c| c.name.eq_ignore_ascii_case(&definition.name)) {
+            Some(existing) => *existing = definition,
+            None => self.charsets.push(definition),
+        }
+    }
+
+    /// 按名称（不区分大小写）查找字符集
+    pub fn get(&self, name: &str) -> Option<&CharsetDefinition> {
+        self.charsets.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CharsetDefinition> {
+        self.charsets.iter()
+    }
+}
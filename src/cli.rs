@@ -1,15 +1,29 @@
-use std::error::Error;
+use std::io::IsTerminal;
 use clap::{Arg, Command};
-use crate::ascii_mapping::{AsciiConfig, Charset};
-use crate::custom_charset_util::sort_charset_by_density;
+use clap::parser::ValueSource;
+use ascii_art_generator::{AlphaMode, AsciiArtError, AsciiToImageRenderer, AsciiConfig, Charset, CharsetRegistry, ColorDepth, ColorMode, FontChain, ImageRenderOptions, LuminanceModel, OutputOptions, Rasterization, PreprocessStep, Preprocessing, RenderMode, ResampleFilter, Result, SvgOptions, ToneMapping};
+use ascii_art_generator::ascii_mapping::parse_hex_color;
+use ascii_art_generator::ascii_to_image::{DEFAULT_GIF_FONT_SIZE, DEFAULT_IMAGE_FONT_SIZE, MAX_SUPERSAMPLE};
+use ascii_art_generator::custom_charset_util::CharsetAnalyzer;
+use ascii_art_generator::dithering::DitherMode;
 
 pub struct CliArgs {
     pub input_path: String,
     pub output_path: Option<String>,
     pub config: AsciiConfig,
+    pub preprocessing: Preprocessing,
+    pub output: OutputOptions,
 }
 
-pub fn parse_args() -> Result<CliArgs, Box<dyn Error>> {
+/// 命令行请求的操作
+pub enum CliCommand {
+    /// 将输入图像转换为字符画
+    Convert(Box<CliArgs>),
+    /// 列出所有可用的字符集（包括从 --charset-file 加载的）
+    ListCharsets(CharsetRegistry),
+}
+
+pub fn parse_args() -> Result<CliCommand> {
     let matches = Command::new("ASCII Art Generator")
         .version(env!("CARGO_PKG_VERSION"))
         .author("Limpid")
@@ -19,7 +33,7 @@ pub fn parse_args() -> Result<CliArgs, Box<dyn Error>> {
                 .short('i')
                 .long("input")
                 .help("Input image file")
-                .required(true)
+                .required_unless_present("list-charsets")
                 .value_name("FILE"),
         )
         .arg(
@@ -44,6 +58,32 @@ pub fn parse_args() -> Result<CliArgs, Box<dyn Error>> {
                 .help("Height of the output ASCII art")
                 .value_name("HEIGHT"),
         )
+        .arg(
+            Arg::new("cell-aspect")
+                .long("cell-aspect")
+                .help("Width/height ratio of a character cell used for automatic height (defaults to the embedded font metrics)")
+                .value_name("RATIO"),
+        )
+        .arg(
+            Arg::new("fit")
+                .long("fit")
+                .help("Fit the output into a --width x --height box while preserving the image aspect ratio")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .help("Resampling filter (box, nearest, bilinear or lanczos)")
+                .value_name("FILTER")
+                .default_value("box"),
+        )
+        .arg(
+            Arg::new("matte")
+                .long("matte")
+                .help("Background color (#rrggbb) that transparent pixels are blended onto, or 'transparent' to keep transparency")
+                .value_name("COLOR")
+                .default_value("#000000"),
+        )
         .arg(
             Arg::new("gamma")
                 .short('g')
@@ -52,6 +92,32 @@ pub fn parse_args() -> Result<CliArgs, Box<dyn Error>> {
                 .value_name("GAMMA")
                 .default_value("1.0"),
         )
+        .arg(
+            Arg::new("luminance")
+                .long("luminance")
+                .help("Luminance model (rec601, rec709, lstar or max)")
+                .value_name("MODEL")
+                .default_value("rec601"),
+        )
+        .arg(
+            Arg::new("tone")
+                .long("tone")
+                .help("Contrast adjustment applied before gamma (none, auto-levels, equalize or clahe)")
+                .value_name("MODE")
+                .default_value("none"),
+        )
+        .arg(
+            Arg::new("tone-clip")
+                .long("tone-clip")
+                .help("Percentage clipped at each end for auto-levels, or the clip limit for clahe")
+                .value_name("VALUE"),
+        )
+        .arg(
+            Arg::new("clahe-tiles")
+                .long("clahe-tiles")
+                .help("Number of tiles per axis for clahe")
+                .value_name("TILES"),
+        )
         .arg(
             Arg::new("color")
                 .short('c')
@@ -59,6 +125,27 @@ pub fn parse_args() -> Result<CliArgs, Box<dyn Error>> {
                 .help("Enable color output")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("color-mode")
+                .long("color-mode")
+                .help("How cell colors are applied (foreground, background or combined); implies --color")
+                .value_name("MODE")
+                .default_value("foreground"),
+        )
+        .arg(
+            Arg::new("color-depth")
+                .long("color-depth")
+                .help("Color depth of ANSI output (auto, truecolor, 256, 16 or none)")
+                .value_name("DEPTH")
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("color-tolerance")
+                .long("color-tolerance")
+                .help("Per-channel difference (0-255) below which adjacent colors are merged in ANSI output")
+                .value_name("TOLERANCE")
+                .default_value("0"),
+        )
         .arg(
             Arg::new("invert")
                 .short('v')
@@ -69,45 +156,312 @@ pub fn parse_args() -> Result<CliArgs, Box<dyn Error>> {
         .arg(
             Arg::new("charset")
                 .long("charset")
-                .help("Character set to use (default, simple, block or pixel)")
+                .help("Name of the character set to use (see --list-charsets)")
                 .value_name("CHARSET")
                 .default_value("default")
         )
+        .arg(
+            Arg::new("charset-file")
+                .long("charset-file")
+                .help("TOML or JSON file with additional named character sets")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("font")
+                .long("font")
+                .help("TTF/OTF font used to measure glyph densities and render images (defaults to the charset's font, then the embedded font)")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("fallback-font")
+                .long("fallback-font")
+                .help("Font searched for glyphs missing from --font, may be given several times")
+                .value_name("FILE")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("list-charsets")
+                .long("list-charsets")
+                .help("List the available character sets and exit")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("custom-charset")
                 .long("custom-charset")
                 .help("Custom Character set to use ([option: --charset] will be ignored)")
                 .value_name("CHARSET")
         )
+        .arg(
+            Arg::new("charset-levels")
+                .long("charset-levels")
+                .help("Pick this many glyphs with evenly spaced densities from the custom charset")
+                .value_name("LEVELS"),
+        )
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .help("Glyph mapping mode (density, structural, half-block, quadrant or sextant)")
+                .value_name("MODE")
+                .default_value("density")
+        )
+        .arg(
+            Arg::new("dither")
+                .long("dither")
+                .help("Dithering mode (none, floyd-steinberg, atkinson, bayer4 or bayer8)")
+                .value_name("MODE")
+                .default_value("none")
+        )
+        .arg(
+            Arg::new("edges")
+                .long("edges")
+                .help("Use directional glyphs (| / - \\ _) where edges are strong")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("edge-threshold")
+                .long("edge-threshold")
+                .help("Normalized gradient magnitude (0.0-1.0) above which edge glyphs are used")
+                .value_name("THRESHOLD")
+                .default_value("0.1")
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .help("Luminance threshold (0-255) for braille and monochrome block modes")
+                .value_name("THRESHOLD")
+                .default_value("128")
+        )
+        .arg(
+            Arg::new("preprocess")
+                .long("preprocess")
+                .help("TOML or JSON file listing preprocessing steps, applied before the steps given on the command line")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("auto-orient")
+                .long("auto-orient")
+                .help("Rotate/flip the image according to its EXIF orientation")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("crop")
+                .long("crop")
+                .help("Crop the image to the rectangle X,Y,WIDTH,HEIGHT")
+                .value_name("X,Y,W,H"),
+        )
+        .arg(
+            Arg::new("rotate")
+                .long("rotate")
+                .help("Rotate the image clockwise by 90, 180 or 270 degrees")
+                .value_name("DEGREES"),
+        )
+        .arg(
+            Arg::new("flip-h")
+                .long("flip-h")
+                .help("Flip the image horizontally")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("flip-v")
+                .long("flip-v")
+                .help("Flip the image vertically")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("brightness")
+                .long("brightness")
+                .help("Brightness offset added to each channel (-255 to 255)")
+                .value_name("VALUE")
+                .allow_negative_numbers(true),
+        )
+        .arg(
+            Arg::new("contrast")
+                .long("contrast")
+                .help("Contrast adjustment in percent (negative values reduce contrast)")
+                .value_name("PERCENT")
+                .allow_negative_numbers(true),
+        )
+        .arg(
+            Arg::new("saturation")
+                .long("saturation")
+                .help("Saturation factor (1.0 keeps the image unchanged, 0.0 is grayscale)")
+                .value_name("FACTOR"),
+        )
+        .arg(
+            Arg::new("blur")
+                .long("blur")
+                .help("Gaussian blur sigma")
+                .value_name("SIGMA"),
+        )
+        .arg(
+            Arg::new("sharpen")
+                .long("sharpen")
+                .help("Unsharp mask sigma")
+                .value_name("SIGMA"),
+        )
+        .arg(
+            Arg::new("font-size")
+                .long("font-size")
+                .help("Font size in pixels for image and GIF output (defaults to 32 for images, 16 for GIF)")
+                .value_name("PIXELS"),
+        )
+        .arg(
+            Arg::new("cell-width")
+                .long("cell-width")
+                .help("Character cell width in pixels for image output (defaults to the font's advance width)")
+                .value_name("PIXELS"),
+        )
+        .arg(
+            Arg::new("line-height")
+                .long("line-height")
+                .help("Line height in pixels for image output (defaults to the font size)")
+                .value_name("PIXELS"),
+        )
+        .arg(
+            Arg::new("padding")
+                .long("padding")
+                .help("Margin in pixels around image output")
+                .value_name("PIXELS")
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("background")
+                .long("background")
+                .help("Background color (#rrggbb) of image output, or 'transparent'")
+                .value_name("COLOR")
+                .default_value("#0c0c0c"),
+        )
+        .arg(
+            Arg::new("foreground")
+                .long("foreground")
+                .help("Default text color (#rrggbb) of image output")
+                .value_name("COLOR")
+                .default_value("#cccccc"),
+        )
+        .arg(
+            Arg::new("svg-font-family")
+                .long("svg-font-family")
+                .help("CSS font-family of SVG output")
+                .value_name("FAMILY"),
+        )
+        .arg(
+            Arg::new("svg-embed-font")
+                .long("svg-embed-font")
+                .help("Embed the used glyphs of the fonts into SVG output")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("rasterize")
+                .long("rasterize")
+                .help("Glyph rasterization for image output: smooth (anti-aliased) or crisp (pixel-snapped bitmap look)")
+                .value_name("MODE")
+                .default_value("smooth"),
+        )
+        .arg(
+            Arg::new("supersample")
+                .long("supersample")
+                .help("Samples per pixel in each direction when rasterizing glyphs (1-8)")
+                .value_name("FACTOR")
+                .default_value("1"),
+        )
         .get_matches();
 
+    let mut registry = CharsetRegistry::builtin();
+    if let Some(path) = matches.get_one::<String>("charset-file") {
+        registry.load_file(path)?;
+    }
+
+    if matches.get_flag("list-charsets") {
+        return Ok(CliCommand::ListCharsets(registry));
+    }
+
     let input_path = matches
         .get_one::<String>("input")
-        .ok_or_else(|| "Input file is required.")?
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Input file is required.".to_string()))?
         .clone();
 
-    let output_path_ref = matches
-        .get_one::<String>("output");
-
-    let output_path= match output_path_ref {
-        Some(s) => Some(s.clone()),
-        None => None
-    };
+    let output_path = matches
+        .get_one::<String>("output")
+        .cloned();
 
     let width = matches
         .get_one::<String>("width")
         .and_then(|w| w.parse::<u32>().ok())
-        .ok_or_else(|| "Invalid width value.")?;
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid width value.".to_string()))?;
 
     let height = matches.get_one::<String>("height")
         .and_then(|h| h.parse::<u32>().ok())
         .unwrap_or(0);  // 0表示需要根据图像比例计算
 
+    let cell_aspect = matches.get_one::<String>("cell-aspect")
+        .map(|r| r.parse::<f32>().ok()
+            .filter(|r| *r > 0.0)
+            .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid cell aspect ratio.".to_string())))
+        .transpose()?;
+
+    let fit = matches.get_flag("fit");
+
+    let filter = matches.get_one::<String>("filter")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid filter.".to_string()))?
+        .parse::<ResampleFilter>()?;
+
+    let luminance_model = matches.get_one::<String>("luminance")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid luminance model.".to_string()))?
+        .parse::<LuminanceModel>()?;
+
+    let tone_clip = matches.get_one::<String>("tone-clip")
+        .map(|c| c.parse::<f32>().ok().filter(|c| *c >= 0.0))
+        .map(|c| c.ok_or_else(|| AsciiArtError::InvalidArgument("Invalid tone clip value.".to_string())))
+        .transpose()?;
+
+    let clahe_tiles = matches.get_one::<String>("clahe-tiles")
+        .map(|t| t.parse::<u32>().ok().filter(|t| *t > 0))
+        .map(|t| t.ok_or_else(|| AsciiArtError::InvalidArgument("Invalid CLAHE tiles value.".to_string())))
+        .transpose()?;
+
+    let tone_mapping = match matches.get_one::<String>("tone")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid tone mode.".to_string()))?
+        .parse::<ToneMapping>()?
+    {
+        ToneMapping::AutoLevels { clip } => ToneMapping::AutoLevels { clip: tone_clip.unwrap_or(clip) },
+        ToneMapping::Clahe { clip_limit, tiles } => ToneMapping::Clahe {
+            clip_limit: tone_clip.unwrap_or(clip_limit),
+            tiles: clahe_tiles.unwrap_or(tiles),
+        },
+        tone_mapping => tone_mapping,
+    };
+
+    let alpha_mode = matches.get_one::<String>("matte")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid matte color.".to_string()))?
+        .parse::<AlphaMode>()?;
+
     let gamma = matches.get_one::<String>("gamma")
         .and_then(|g| g.parse::<f32>().ok())
-        .ok_or_else(|| "Invalid gamma value")?;
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid gamma value".to_string()))?;
+
+    let color_mode = matches.get_one::<String>("color-mode")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid color mode.".to_string()))?
+        .parse::<ColorMode>()?;
+
+    // 显式指定颜色模式时自动启用彩色输出
+    let color = matches.get_flag("color")
+        || matches.value_source("color-mode") == Some(ValueSource::CommandLine);
 
-    let color = matches.get_flag("color");
+    let color_depth = match matches.get_one::<String>("color-depth").map(String::as_str) {
+        // 仅在输出到终端时根据环境变量推断，写入文件时保留真彩色
+        Some("auto") | None => {
+            if output_path.is_none() && std::io::stdout().is_terminal() {
+                ColorDepth::detect()
+            } else {
+                ColorDepth::TrueColor
+            }
+        }
+        Some(depth) => depth.parse::<ColorDepth>()?,
+    };
+
+    let color_tolerance = matches.get_one::<String>("color-tolerance")
+        .and_then(|t| t.parse::<u8>().ok())
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid color tolerance value.".to_string()))?;
 
     let invert = matches.get_flag("invert");
 
@@ -115,33 +469,252 @@ pub fn parse_args() -> Result<CliArgs, Box<dyn Error>> {
         .unwrap_or(&String::new())
         .clone();
 
+    // 未被字符集文件覆盖的内置字符集使用对应的枚举值，其余字符集作为自定义字符集处理
+    let mut presorted = false;
+    let mut charset_font = None;
     let charset = if custom_charset.is_empty() {
-        matches.get_one::<String>("charset")
-            .and_then(|s| s.parse::<Charset>().ok())
-            .ok_or_else(|| "Invalid charset value.")?
+        let name = matches.get_one::<String>("charset")
+            .ok_or_else(|| AsciiArtError::InvalidCharset("Invalid charset value.".to_string()))?;
+        let definition = registry.get(name)
+            .ok_or_else(|| AsciiArtError::InvalidCharset(format!("Unknown charset: {name} (see --list-charsets)")))?;
+
+        match name.parse::<Charset>() {
+            Ok(charset) if definition.glyphs == charset.as_str() => charset,
+            _ => {
+                custom_charset = definition.glyphs.clone();
+                presorted = definition.sorted;
+                charset_font = definition.font.clone();
+                Charset::CUSTOM
+            }
+        }
     } else {
         Charset::CUSTOM
     };
 
-    // 自定义字符集处理
-    if !custom_charset.is_empty() {
-        custom_charset = sort_charset_by_density(custom_charset);
+    let mode = matches.get_one::<String>("mode")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid mode.".to_string()))?
+        .parse::<RenderMode>()?;
+
+    let dither = matches.get_one::<String>("dither")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid dither mode.".to_string()))?
+        .parse::<DitherMode>()?;
+
+    let edge_detection = matches.get_flag("edges");
+
+    let edge_threshold = matches.get_one::<String>("edge-threshold")
+        .and_then(|t| t.parse::<f32>().ok())
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid edge threshold value.".to_string()))?;
+
+    let threshold = matches.get_one::<String>("threshold")
+        .and_then(|t| t.parse::<u8>().ok())
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid threshold value.".to_string()))?;
+
+    let charset_levels = matches.get_one::<String>("charset-levels")
+        .map(|l| l.parse::<usize>().ok().filter(|l| *l >= 2))
+        .map(|l| l.ok_or_else(|| AsciiArtError::InvalidArgument("Invalid charset levels value.".to_string())))
+        .transpose()?;
+
+    // 字体链：--font 或字符集推荐的字体为主字体，其后为后备字体，最后为内嵌字体
+    let font_paths: Vec<String> = matches.get_one::<String>("font").cloned()
+        .or(charset_font)
+        .into_iter()
+        .chain(matches.get_many::<String>("fallback-font").into_iter().flatten().cloned())
+        .collect();
+    let font = FontChain::load(&font_paths)?;
+
+    let image = parse_image_options(&matches)?;
+
+    // 未指定单元格宽高比时按主字体的度量计算；指定了单元格宽度或行高时按渲染图像的单元格尺寸计算，
+    // 使图像和 GIF 输出不被拉伸
+    let cell_aspect = match cell_aspect {
+        Some(cell_aspect) => cell_aspect,
+        None if image.cell_width.is_some() || image.line_height.is_some() => {
+            let default_font_size = if output_path.as_deref().is_some_and(|p| p.ends_with(".gif")) {
+                DEFAULT_GIF_FONT_SIZE
+            } else {
+                DEFAULT_IMAGE_FONT_SIZE
+            };
+            let (cell_width, line_height) = AsciiToImageRenderer::from_options(&image, default_font_size)?
+                .with_fonts(font.clone())
+                .cell_size();
+            cell_width as f32 / line_height as f32
+        }
+        None => font.cell_aspect_ratio(),
+    };
+
+    // 自定义字符集处理：去重、按密度排序，可选择挑选密度均匀分布的字符
+    if !custom_charset.is_empty() && !presorted {
+        let mut analysis = CharsetAnalyzer::with_fonts(font.clone()).analyze(&custom_charset);
+        if !analysis.missing.is_empty() {
+            let missing: String = analysis.missing.iter().collect();
+            eprintln!("Warning: the font has no glyphs for {:?}, these characters are ignored", missing);
+        }
+        if let Some(levels) = charset_levels {
+            analysis = analysis.spread(levels);
+        }
+        custom_charset = analysis.charset();
     }
 
     let config = AsciiConfig {
         width,
         height,
+        cell_aspect,
+        fit,
+        filter,
         gamma,
+        luminance_model,
+        tone_mapping,
         color,
+        color_mode,
+        color_depth,
+        color_tolerance,
         charset,
         custom_charset,
+        presorted,
         invert,
-        ..AsciiConfig::default()
+        mode,
+        dither,
+        edge_detection,
+        edge_threshold,
+        threshold,
+        alpha_mode,
+        font,
+    };
+
+    let preprocessing = parse_preprocessing(&matches)?;
+
+    let output = OutputOptions {
+        image,
+        svg: SvgOptions {
+            font_family: matches.get_one::<String>("svg-font-family")
+                .cloned()
+                .unwrap_or_else(|| SvgOptions::default().font_family),
+            embed_font: matches.get_flag("svg-embed-font"),
+        },
     };
 
-    Ok(CliArgs {
+    Ok(CliCommand::Convert(Box::new(CliArgs {
         input_path,
         output_path,
-        config
-    })
-}
\ No newline at end of file
+        config,
+        preprocessing,
+        output,
+    })))
+}
+
+// 预处理配置文件中的步骤在前，命令行指定的步骤按固定顺序追加在后
+fn parse_preprocessing(matches: &clap::ArgMatches) -> Result<Preprocessing> {
+    let mut preprocessing = match matches.get_one::<String>("preprocess") {
+        Some(path) => Preprocessing::from_file(path)?,
+        None => Preprocessing::default(),
+    };
+    preprocessing.auto_orient |= matches.get_flag("auto-orient");
+
+    if let Some(crop) = matches.get_one::<String>("crop") {
+        let values = crop.split(',').map(|v| v.trim().parse::<u32>().ok()).collect::<Option<Vec<_>>>();
+        let Some(&[x, y, width, height]) = values.as_deref() else {
+            return Err(AsciiArtError::InvalidArgument("Invalid crop rectangle.".to_string()));
+        };
+        preprocessing.steps.push(PreprocessStep::Crop { x, y, width, height });
+    }
+
+    if let Some(rotate) = matches.get_one::<String>("rotate") {
+        let degrees = rotate.parse::<u32>().ok()
+            .filter(|d| matches!(d, 90 | 180 | 270))
+            .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid rotation, expected 90, 180 or 270.".to_string()))?;
+        preprocessing.steps.push(PreprocessStep::Rotate { degrees });
+    }
+
+    if matches.get_flag("flip-h") {
+        preprocessing.steps.push(PreprocessStep::FlipHorizontal);
+    }
+    if matches.get_flag("flip-v") {
+        preprocessing.steps.push(PreprocessStep::FlipVertical);
+    }
+
+    if let Some(brightness) = matches.get_one::<String>("brightness") {
+        let value = brightness.parse::<i32>().ok()
+            .filter(|v| (-255..=255).contains(v))
+            .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid brightness value.".to_string()))?;
+        preprocessing.steps.push(PreprocessStep::Brightness { value });
+    }
+
+    if let Some(contrast) = matches.get_one::<String>("contrast") {
+        let value = contrast.parse::<f32>()
+            .map_err(|_| AsciiArtError::InvalidArgument("Invalid contrast value.".to_string()))?;
+        preprocessing.steps.push(PreprocessStep::Contrast { value });
+    }
+
+    if let Some(saturation) = matches.get_one::<String>("saturation") {
+        let factor = saturation.parse::<f32>().ok()
+            .filter(|f| *f >= 0.0)
+            .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid saturation value.".to_string()))?;
+        preprocessing.steps.push(PreprocessStep::Saturation { factor });
+    }
+
+    if let Some(blur) = matches.get_one::<String>("blur") {
+        let sigma = blur.parse::<f32>().ok()
+            .filter(|s| *s > 0.0)
+            .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid blur sigma.".to_string()))?;
+        preprocessing.steps.push(PreprocessStep::Blur { sigma });
+    }
+
+    if let Some(sharpen) = matches.get_one::<String>("sharpen") {
+        let sigma = sharpen.parse::<f32>().ok()
+            .filter(|s| *s > 0.0)
+            .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid sharpen sigma.".to_string()))?;
+        preprocessing.steps.push(PreprocessStep::Sharpen { sigma, threshold: 0 });
+    }
+
+    Ok(preprocessing)
+}
+
+// 图像和 GIF 输出的渲染设置
+fn parse_image_options(matches: &clap::ArgMatches) -> Result<ImageRenderOptions> {
+    let pixels = |name: &str, message: &str| {
+        matches.get_one::<String>(name)
+            .map(|v| v.parse::<u32>().ok()
+                .filter(|v| *v > 0)
+                .ok_or_else(|| AsciiArtError::InvalidArgument(message.to_string())))
+            .transpose()
+    };
+
+    let padding = matches.get_one::<String>("padding")
+        .and_then(|p| p.parse::<u32>().ok())
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid padding value.".to_string()))?;
+
+    let rasterization = matches.get_one::<String>("rasterize")
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid rasterization mode.".to_string()))?
+        .parse::<Rasterization>()?;
+
+    let supersample = matches.get_one::<String>("supersample")
+        .and_then(|f| f.parse::<u32>().ok())
+        .filter(|f| (1..=MAX_SUPERSAMPLE).contains(f))
+        .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid supersample factor.".to_string()))?;
+
+    let mut options = ImageRenderOptions {
+        font_size: pixels("font-size", "Invalid font size.")?,
+        cell_width: pixels("cell-width", "Invalid cell width.")?,
+        line_height: pixels("line-height", "Invalid line height.")?,
+        padding,
+        rasterization,
+        supersample,
+        ..ImageRenderOptions::default()
+    };
+
+    match matches.get_one::<String>("background") {
+        Some(background) if background.eq_ignore_ascii_case("transparent") => options.transparent_background = true,
+        Some(background) => {
+            options.background = parse_hex_color(background)
+                .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid background color.".to_string()))?;
+        }
+        None => {}
+    }
+
+    if let Some(foreground) = matches.get_one::<String>("foreground") {
+        options.foreground = parse_hex_color(foreground)
+            .ok_or_else(|| AsciiArtError::InvalidArgument("Invalid foreground color.".to_string()))?;
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,169 @@
+use crate::error::AsciiArtError;
+use image::Rgb;
+use std::sync::OnceLock;
+
+// xterm 默认的 16 色调色板
+const ANSI_16_PALETTE: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+// xterm 256 色中 6x6x6 色立方体每个分量的取值
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// 终端支持的颜色深度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorDepth {
+    /// 24 位真彩色
+    #[default]
+    TrueColor,
+    /// xterm 256 色
+    Ansi256,
+    /// 标准 16 色
+    Ansi16,
+    /// 不输出颜色
+    None,
+}
+
+impl ColorDepth {
+    /// 根据 NO_COLOR、COLORTERM 和 TERM 环境变量推断终端的颜色深度
+    pub fn detect() -> Self {
+        if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return ColorDepth::None;
+        }
+
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default().to_lowercase();
+        if colorterm.contains("truecolor") || colorterm.contains("24bit") {
+            return ColorDepth::TrueColor;
+        }
+
+        match std::env::var("TERM") {
+            Ok(term) if term == "dumb" => ColorDepth::None,
+            Ok(term) if term.contains("256color") => ColorDepth::Ansi256,
+            Ok(term) if term.contains("truecolor") || term.contains("direct") => ColorDepth::TrueColor,
+            Ok(term) if !term.is_empty() => ColorDepth::Ansi16,
+            // 未设置 TERM（如 Windows 终端）时假定支持真彩色
+            _ => ColorDepth::TrueColor,
+        }
+    }
+
+    /// 设置前景色的 SGR 转义序列
+    pub fn foreground_sgr(&self, color: Rgb<u8>) -> Option<String> {
+        match self {
+            ColorDepth::TrueColor => Some(format!("\x1B[38;2;{};{};{}m", color[0], color[1], color[2])),
+            ColorDepth::Ansi256 => Some(format!("\x1B[38;5;{}m", nearest_256(color))),
+            ColorDepth::Ansi16 => {
+                let index = nearest_16(color);
+                let code = if index < 8 { 30 + index } else { 90 + index - 8 };
+                Some(format!("\x1B[{}m", code))
+            }
+            ColorDepth::None => None,
+        }
+    }
+
+    /// 设置背景色的 SGR 转义序列
+    pub fn background_sgr(&self, color: Rgb<u8>) -> Option<String> {
+        match self {
+            ColorDepth::TrueColor => Some(format!("\x1B[48;2;{};{};{}m", color[0], color[1], color[2])),
+            ColorDepth::Ansi256 => Some(format!("\x1B[48;5;{}m", nearest_256(color))),
+            ColorDepth::Ansi16 => {
+                let index = nearest_16(color);
+                let code = if index < 8 { 40 + index } else { 100 + index - 8 };
+                Some(format!("\x1B[{}m", code))
+            }
+            ColorDepth::None => None,
+        }
+    }
+}
+
+impl std::str::FromStr for ColorDepth {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "truecolor" | "24bit" => Ok(ColorDepth::TrueColor),
+            "256" | "xterm-256" => Ok(ColorDepth::Ansi256),
+            "16" | "ansi-16" => Ok(ColorDepth::Ansi16),
+            "none" => Ok(ColorDepth::None),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的颜色深度: {s}"))),
+        }
+    }
+}
+
+/// xterm 256 色调色板中索引为 `index` 的颜色
+pub fn palette_256(index: u8) -> Rgb<u8> {
+    match index {
+        0..=15 => Rgb(ANSI_16_PALETTE[index as usize]),
+        16..=231 => {
+            let i = index - 16;
+            Rgb([
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            ])
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            Rgb([gray, gray, gray])
+        }
+    }
+}
+
+// 在 256 色调色板中查找感知上最接近的颜色，跳过各终端定义不一致的前 16 色
+fn nearest_256(color: Rgb<u8>) -> u8 {
+    static PALETTE: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    let palette = PALETTE.get_or_init(|| (16..=255).map(|i| srgb_to_lab(palette_256(i))).collect());
+    16 + nearest_index(palette, color) as u8
+}
+
+fn nearest_16(color: Rgb<u8>) -> u8 {
+    static PALETTE: OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+    let palette = PALETTE.get_or_init(|| ANSI_16_PALETTE.iter().map(|&c| srgb_to_lab(Rgb(c))).collect());
+    nearest_index(palette, color) as u8
+}
+
+// 按 CIELAB 空间中的欧氏距离 (ΔE76) 查找最接近的颜色
+fn nearest_index(palette: &[[f32; 3]], color: Rgb<u8>) -> usize {
+    let lab = srgb_to_lab(color);
+    palette
+        .iter()
+        .map(|p| (p[0] - lab[0]).powi(2) + (p[1] - lab[1]).powi(2) + (p[2] - lab[2]).powi(2))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn srgb_to_lab(color: Rgb<u8>) -> [f32; 3] {
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    let (r, g, b) = (linear(color[0]), linear(color[1]), linear(color[2]));
+
+    // sRGB -> XYZ (D65)，并按白点归一化
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
@@ -0,0 +1,15 @@
+use crate::error::{AsciiArtError, Result};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// 读取 TOML 或 JSON（按扩展名区分，默认为 TOML）配置文件
+///
+/// 文件读取失败返回 I/O 错误，内容无效时返回 `invalid` 根据解析错误信息构造的错误。
+pub fn load_config<T: DeserializeOwned>(path: &str, invalid: impl Fn(String) -> AsciiArtError) -> Result<T> {
+    let content = std::fs::read_to_string(path)?;
+
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&content).map_err(|e| invalid(e.to_string())),
+        _ => toml::from_str(&content).map_err(|e| invalid(e.to_string())),
+    }
+}
@@ -1,74 +1,177 @@
-use image::{ImageBuffer, Luma};
+use crate::font_chain::FontChain;
 use rusttype::{Font, Point, Scale};
 
-pub fn sort_charset_by_density(charset: String) -> String {
-    let font_data = include_bytes!("../res/DejaVuSansMono.ttf") as &[u8];
-    let font = Font::try_from_bytes(font_data).expect("Fail to load font");
-
-    let scale = Scale::uniform(24.0);
+// 测量字符密度时使用的字号（行高像素数）
+const MEASURE_SIZE: f32 = 48.0;
 
-    let mut char_densities: Vec<(char, u32)> = charset
-        .chars()
-        .map(|c| {
-            let density = calculate_char_density(&font, scale, c);
-            (c, density)
-        })
-        .collect();
+/// 字符及其在字符单元格中的墨迹覆盖率
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphDensity {
+    pub glyph: char,
+    /// 字形覆盖的面积占字符单元格（前进宽度 x 行高）的比例 (0.0-1.0)
+    pub density: f32,
+}
 
-    char_densities.sort_by_key(|&(_, d)| d);
-    char_densities.into_iter().map(|(c, _)| c).collect()
+/// 字符集的分析结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CharsetAnalysis {
+    /// 去重后按密度从低到高排列的字符
+    pub glyphs: Vec<GlyphDensity>,
+    /// 字体中不存在的字符（会显示为 .notdef 方框），不包含在 `glyphs` 中
+    pub missing: Vec<char>,
 }
 
-fn calculate_char_density(font: &Font, scale: Scale, c: char) -> u32 {
-    let size = 32;
-    let mut img: ImageBuffer<Luma<u8>, Vec<u8>> = ImageBuffer::new(size, size);
+impl CharsetAnalysis {
+    /// 按密度排列的字符集
+    pub fn charset(&self) -> String {
+        self.glyphs.iter().map(|g| g.glyph).collect()
+    }
 
-    for pixel in img.pixels_mut() {
-        *pixel = Luma([255])
+    /// 归一化到 0.0-1.0 的密度，最稀疏的字符为 0，最密集的字符为 1
+    pub fn normalized_densities(&self) -> Vec<f32> {
+        let min = self.glyphs.first().map_or(0.0, |g| g.density);
+        let max = self.glyphs.last().map_or(0.0, |g| g.density);
+        self.glyphs
+            .iter()
+            .map(|g| if max > min { (g.density - min) / (max - min) } else { 0.0 })
+            .collect()
     }
 
-    let point = Point { x: 0.0, y: scale.y };
+    /// 从字符集中挑选最多 `levels` 个字符，使其密度尽量均匀分布在最小值和最大值之间
+    pub fn spread(&self, levels: usize) -> CharsetAnalysis {
+        if levels >= self.glyphs.len() || levels < 2 {
+            return self.clone();
+        }
 
-    let glyph = font.glyph(c)
-        .scaled(scale)
-        .positioned(point);
+        let (min, max) = (self.glyphs[0].density, self.glyphs[self.glyphs.len() - 1].density);
+        let mut glyphs: Vec<GlyphDensity> = Vec::with_capacity(levels);
+        let mut start = 0;
+        for i in 0..levels {
+            let target = min + (max - min) * i as f32 / (levels - 1) as f32;
+            // 每个目标只在尚未使用的字符中选择，并为后续目标保留足够的字符
+            let end = self.glyphs.len() - (levels - 1 - i);
+            let best = (start..end)
+                .min_by(|&a, &b| {
+                    (self.glyphs[a].density - target).abs().total_cmp(&(self.glyphs[b].density - target).abs())
+                })
+                .unwrap_or(start);
+            glyphs.push(self.glyphs[best]);
+            start = best + 1;
+        }
 
-    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0, 0, 0, 0);
-    let mut buf = vec![0.0; size as usize * size as usize];
+        CharsetAnalysis { glyphs, missing: self.missing.clone() }
+    }
+}
 
-    glyph.draw(|x, y, coverage| {
-        let pixel_x = x;
-        let pixel_y = y;
+/// 使用字体测量字符密度
+pub struct CharsetAnalyzer {
+    fonts: FontChain,
+    scale: Scale,
+}
 
-        // 更新边界
-        if pixel_x < min_x { min_x = pixel_x; }
-        if pixel_y < min_y { min_y = pixel_y; }
-        if pixel_x > max_x { max_x = pixel_x; }
-        if pixel_y > max_y { max_y = pixel_y; }
+impl Default for CharsetAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CharsetAnalyzer {
+    /// 使用内嵌字体 DejaVuSansMono
+    pub fn new() -> Self {
+        Self::with_fonts(FontChain::embedded())
+    }
+
+    /// 使用单个字体，字体中缺少的字符回退到内嵌字体
+    pub fn with_font(font: Font<'static>) -> Self {
+        Self::with_fonts(FontChain::new(vec![font]))
+    }
+
+    /// 每个字符在字体链中第一个包含它的字体中测量
+    pub fn with_fonts(fonts: FontChain) -> Self {
+        CharsetAnalyzer { fonts, scale: Scale::uniform(MEASURE_SIZE) }
+    }
 
-        // 将覆盖值存储到缓冲区中
-        let index = (pixel_y * size + pixel_x) as usize;
-        buf[index] = coverage;
-    });
+    /// 字体链中是否包含该字符（空白字符总是视为存在）
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.fonts.has_glyph(c)
+    }
 
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let pixel_x = x;
-            let pixel_y = y;
+    /// 字符在其字符单元格中的覆盖率 (0.0-1.0)
+    pub fn density(&self, c: char) -> f32 {
+        let font = self.fonts.font_for(c).unwrap_or(self.fonts.primary());
+        let (buf, _, _) = render_glyph_cell(font, self.scale, c);
+        buf.iter().sum::<f32>() / buf.len() as f32
+    }
 
-            if pixel_x >= size || pixel_y >= size {
+    /// 去重、剔除字体中缺失的字符，并按密度从低到高排序
+    pub fn analyze(&self, charset: &str) -> CharsetAnalysis {
+        let mut analysis = CharsetAnalysis::default();
+        for c in charset.chars().filter(|c| !c.is_control()) {
+            if analysis.glyphs.iter().any(|g| g.glyph == c) || analysis.missing.contains(&c) {
                 continue;
             }
 
-            let index = (pixel_y * size + pixel_x) as usize;
-            let coverage = buf[index];
+            if self.has_glyph(c) {
+                analysis.glyphs.push(GlyphDensity { glyph: c, density: self.density(c) });
+            } else {
+                analysis.missing.push(c);
+            }
+        }
 
-            let value = (255.0 * (1.0 - coverage)) as u8;
-            img[(pixel_x, pixel_y)] = Luma([value]);
+        // 稳定排序，密度相同的字符保持原有顺序
+        analysis.glyphs.sort_by(|a, b| a.density.total_cmp(&b.density));
+        analysis
+    }
+}
+
+/// 按内嵌字体中的密度对字符集排序，同时去除重复和字体中缺失的字符
+pub fn sort_charset_by_density(charset: String) -> String {
+    CharsetAnalyzer::new().analyze(&charset).charset()
+}
+
+// 将字符渲染到一个字符单元格（前进宽度 x 行高）中，返回覆盖率缓冲区及其宽高，超出单元格的部分被裁剪
+fn render_glyph_cell(font: &Font, scale: Scale, c: char) -> (Vec<f32>, usize, usize) {
+    let v_metrics = font.v_metrics(scale);
+    let glyph = font.glyph(c).scaled(scale);
+    let cell_w = glyph.h_metrics().advance_width.ceil().max(1.0) as usize;
+    let cell_h = (v_metrics.ascent - v_metrics.descent).ceil().max(1.0) as usize;
+
+    let mut buf = vec![0.0f32; cell_w * cell_h];
+    let glyph = glyph.positioned(Point { x: 0.0, y: v_metrics.ascent });
+    if let Some(bb) = glyph.pixel_bounding_box() {
+        glyph.draw(|x, y, coverage| {
+            let px = bb.min.x + x as i32;
+            let py = bb.min.y + y as i32;
+            if px >= 0 && py >= 0 && (px as usize) < cell_w && (py as usize) < cell_h {
+                buf[py as usize * cell_w + px as usize] = coverage;
+            }
+        });
+    }
+
+    (buf, cell_w, cell_h)
+}
+
+/// 将字符渲染到一个等宽字符单元格中，并降采样为 `cols`x`rows` 的覆盖率矩阵（按行存储，0.0-1.0）
+pub fn glyph_coverage_matrix(font: &Font, scale: Scale, c: char, cols: usize, rows: usize) -> Vec<f32> {
+    let (buf, cell_w, cell_h) = render_glyph_cell(font, scale, c);
+
+    let mut matrix = vec![0.0f32; cols * rows];
+    for row in 0..rows {
+        for col in 0..cols {
+            let (x0, x1) = (col * cell_w / cols, ((col + 1) * cell_w / cols).max(col * cell_w / cols + 1));
+            let (y0, y1) = (row * cell_h / rows, ((row + 1) * cell_h / rows).max(row * cell_h / rows + 1));
+
+            let mut total = 0.0;
+            let mut count = 0;
+            for y in y0..y1.min(cell_h) {
+                for x in x0..x1.min(cell_w) {
+                    total += buf[y * cell_w + x];
+                    count += 1;
+                }
+            }
+            matrix[row * cols + col] = if count > 0 { total / count as f32 } else { 0.0 };
         }
     }
 
-    img.pixels()
-        .filter(|p| p[0] < 200)
-        .count() as u32
-}
\ No newline at end of file
+    matrix
+}
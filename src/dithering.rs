@@ -0,0 +1,136 @@
+use crate::error::AsciiArtError;
+
+const BAYER_4X4: [[u8; 4]; 4] = [
+    [0, 8, 2, 10],
+    [12, 4, 14, 6],
+    [3, 11, 1, 9],
+    [15, 7, 13, 5],
+];
+
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+// 误差扩散核：(dx, dy, 权重)
+const FLOYD_STEINBERG: [(i32, i32, f32); 4] = [
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0),
+    (0, 1, 5.0 / 16.0),
+    (1, 1, 1.0 / 16.0),
+];
+
+const ATKINSON: [(i32, i32, f32); 6] = [
+    (1, 0, 1.0 / 8.0),
+    (2, 0, 1.0 / 8.0),
+    (-1, 1, 1.0 / 8.0),
+    (0, 1, 1.0 / 8.0),
+    (1, 1, 1.0 / 8.0),
+    (0, 2, 1.0 / 8.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DitherMode {
+    #[default]
+    None,
+    FloydSteinberg,
+    Atkinson,
+    Bayer4,
+    Bayer8,
+}
+
+impl std::str::FromStr for DitherMode {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(DitherMode::None),
+            "floyd-steinberg" | "fs" => Ok(DitherMode::FloydSteinberg),
+            "atkinson" => Ok(DitherMode::Atkinson),
+            "bayer4" => Ok(DitherMode::Bayer4),
+            "bayer8" => Ok(DitherMode::Bayer8),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的抖动模式: {s}"))),
+        }
+    }
+}
+
+/// 将亮度网格 (0.0-255.0) 量化为最接近的等级的索引，`levels` 为按升序排列的各等级亮度值
+pub fn quantize_to_levels(luminance: &[f32], width: usize, height: usize, levels: &[f32], mode: DitherMode) -> Vec<usize> {
+    if levels.is_empty() {
+        return vec![0; luminance.len()];
+    }
+
+    match mode {
+        DitherMode::None => luminance.iter().map(|&l| nearest_level(l, levels)).collect(),
+        DitherMode::FloydSteinberg => diffuse_error(luminance, width, height, levels, &FLOYD_STEINBERG),
+        DitherMode::Atkinson => diffuse_error(luminance, width, height, levels, &ATKINSON),
+        DitherMode::Bayer4 => ordered(luminance, width, levels, &BAYER_4X4),
+        DitherMode::Bayer8 => ordered(luminance, width, levels, &BAYER_8X8),
+    }
+}
+
+// 亮度所在的相邻两个等级：(下方等级, 上方等级, 在两者之间的位置 0.0-1.0)
+fn bracket(luminance: f32, levels: &[f32]) -> (usize, usize, f32) {
+    let upper = levels.partition_point(|&v| v <= luminance);
+    if upper == 0 {
+        return (0, 0, 0.0);
+    }
+    if upper == levels.len() {
+        return (upper - 1, upper - 1, 0.0);
+    }
+
+    let (low, high) = (levels[upper - 1], levels[upper]);
+    let t = if high > low { (luminance - low) / (high - low) } else { 0.0 };
+    (upper - 1, upper, t)
+}
+
+fn nearest_level(luminance: f32, levels: &[f32]) -> usize {
+    let (low, high, t) = bracket(luminance, levels);
+    if t >= 0.5 { high } else { low }
+}
+
+fn diffuse_error(luminance: &[f32], width: usize, height: usize, levels: &[f32], kernel: &[(i32, i32, f32)]) -> Vec<usize> {
+    let mut buf = luminance.to_vec();
+    let mut indices = vec![0; buf.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let index = nearest_level(buf[i], levels);
+            indices[i] = index;
+
+            let error = buf[i].clamp(0.0, 255.0) - levels[index];
+            for &(dx, dy, weight) in kernel {
+                let nx = x as i32 + dx;
+                let ny = y as i32 + dy;
+                if nx >= 0 && (nx as usize) < width && (ny as usize) < height {
+                    buf[ny as usize * width + nx as usize] += error * weight;
+                }
+            }
+        }
+    }
+
+    indices
+}
+
+// 按抖动矩阵的阈值在相邻两个等级之间选择
+fn ordered<const N: usize>(luminance: &[f32], width: usize, levels: &[f32], matrix: &[[u8; N]; N]) -> Vec<usize> {
+    let matrix_levels = (N * N) as f32;
+
+    luminance
+        .iter()
+        .enumerate()
+        .map(|(i, &l)| {
+            let (x, y) = (i % width, i / width);
+            let threshold = (matrix[y % N][x % N] as f32 + 0.5) / matrix_levels;
+            let (low, high, t) = bracket(l, levels);
+            if t >= threshold { high } else { low }
+        })
+        .collect()
+}
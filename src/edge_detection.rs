@@ -0,0 +1,89 @@
+use image::DynamicImage;
+
+// 单个像素 Sobel 梯度幅值的最大可能值，用于归一化
+const SOBEL_MAX: f32 = 4.0 * 255.0;
+
+/// 单元格内的边缘信息
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CellEdge {
+    /// 归一化后的平均梯度幅值 (0.0-1.0)
+    pub magnitude: f32,
+    /// 边缘走向角度（度，y 轴朝上，范围 0-180）
+    pub angle: f32,
+    /// 梯度幅值在单元格内的垂直重心 (0.0 为顶部，1.0 为底部)
+    pub centroid_y: f32,
+}
+
+impl CellEdge {
+    /// 根据边缘走向选择方向字符
+    pub fn glyph(&self) -> char {
+        match self.angle {
+            a if !(22.5..157.5).contains(&a) => {
+                if self.centroid_y > 0.66 { '_' } else { '-' }
+            }
+            a if a < 67.5 => '/',
+            a if a < 112.5 => '|',
+            _ => '\\',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct EdgeAccumulator {
+    magnitude: f32,
+    // 使用倍角表示累积方向，避免相反方向的梯度互相抵消
+    cos2: f32,
+    sin2: f32,
+    weighted_y: f32,
+    count: u32,
+}
+
+/// 对图像做 Sobel 运算，并按 `width`x`height` 的单元格网格汇总每个单元格的边缘信息
+pub fn cell_edges(img: &DynamicImage, width: u32, height: u32) -> Vec<CellEdge> {
+    let luma = img.to_luma8();
+    let (img_w, img_h) = luma.dimensions();
+    let mut cells = vec![EdgeAccumulator::default(); (width * height) as usize];
+
+    let at = |x: u32, y: u32| luma.get_pixel(x, y)[0] as f32;
+
+    for y in 1..img_h.saturating_sub(1) {
+        for x in 1..img_w.saturating_sub(1) {
+            let gx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+            let gy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+
+            let cell_x = (x as u64 * width as u64 / img_w as u64) as u32;
+            let cell_y = (y as u64 * height as u64 / img_h as u64) as u32;
+            let cell_top = cell_y as f32 * img_h as f32 / height as f32;
+            let cell_height = img_h as f32 / height as f32;
+
+            let magnitude = (gx * gx + gy * gy).sqrt();
+            let acc = &mut cells[(cell_y * width + cell_x) as usize];
+            acc.magnitude += magnitude;
+            acc.cos2 += gx * gx - gy * gy;
+            acc.sin2 += 2.0 * gx * gy;
+            acc.weighted_y += magnitude * ((y as f32 + 0.5 - cell_top) / cell_height);
+            acc.count += 1;
+        }
+    }
+
+    cells
+        .into_iter()
+        .map(|acc| {
+            if acc.count == 0 || acc.magnitude == 0.0 {
+                return CellEdge::default();
+            }
+
+            // 梯度方向（图像坐标系，y 轴朝下），边缘走向与之垂直
+            let gradient_angle = 0.5 * acc.sin2.atan2(acc.cos2).to_degrees();
+            let angle = (-(gradient_angle + 90.0)).rem_euclid(180.0);
+
+            CellEdge {
+                magnitude: (acc.magnitude / acc.count as f32 / SOBEL_MAX).min(1.0),
+                angle,
+                centroid_y: (acc.weighted_y / acc.magnitude).clamp(0.0, 1.0),
+            }
+        })
+        .collect()
+}
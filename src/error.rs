@@ -0,0 +1,66 @@
+use std::fmt;
+use std::io;
+
+/// 库中所有公开接口返回的错误类型
+#[derive(Debug)]
+pub enum AsciiArtError {
+    /// 图像解码（或编码）失败
+    Decode(image::ImageError),
+    /// 不支持的输出文件扩展名
+    UnsupportedExtension(String),
+    /// 字体加载失败
+    FontLoad(String),
+    /// 不支持或无效的字符集
+    InvalidCharset(String),
+    /// 无效的参数值
+    InvalidArgument(String),
+    /// 文件读写错误
+    Io(io::Error),
+    /// 输出内容序列化失败
+    Serialize(serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, AsciiArtError>;
+
+impl fmt::Display for AsciiArtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiArtError::Decode(e) => write!(f, "Failed to process image: {e}"),
+            AsciiArtError::UnsupportedExtension(ext) => write!(f, "Unsupported file extension: .{ext}"),
+            AsciiArtError::FontLoad(msg) => write!(f, "Failed to load font: {msg}"),
+            AsciiArtError::InvalidCharset(msg) => write!(f, "Invalid charset: {msg}"),
+            AsciiArtError::InvalidArgument(msg) => write!(f, "Invalid argument: {msg}"),
+            AsciiArtError::Io(e) => write!(f, "I/O error: {e}"),
+            AsciiArtError::Serialize(e) => write!(f, "Failed to serialize output: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AsciiArtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AsciiArtError::Decode(e) => Some(e),
+            AsciiArtError::Io(e) => Some(e),
+            AsciiArtError::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for AsciiArtError {
+    fn from(e: image::ImageError) -> Self {
+        AsciiArtError::Decode(e)
+    }
+}
+
+impl From<io::Error> for AsciiArtError {
+    fn from(e: io::Error) -> Self {
+        AsciiArtError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for AsciiArtError {
+    fn from(e: serde_json::Error) -> Self {
+        AsciiArtError::Serialize(e)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::error::{AsciiArtError, Result};
+use rusttype::{Font, GlyphId, Scale};
+use std::sync::{Arc, OnceLock};
+
+// 内嵌字体及其文件数据，总是位于字体链的末尾
+fn embedded_font() -> &'static (Font<'static>, Arc<[u8]>) {
+    static FONT: OnceLock<(Font<'static>, Arc<[u8]>)> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let font_data = include_bytes!("../res/DejaVuSansMono.ttf") as &[u8];
+        let font = Font::try_from_bytes(font_data).expect("embedded font is valid");
+        (font, Arc::from(font_data))
+    })
+}
+
+/// 按顺序查找字符的字体链：第一个字体为主字体，决定字符单元格的度量；
+/// 主字体中缺少的字符依次在后备字体中查找，最后回退到内嵌的 DejaVuSansMono
+#[derive(Clone)]
+pub struct FontChain {
+    fonts: Vec<Font<'static>>,
+    // 每个字体的文件数据，用于嵌入到矢量输出中；直接由 `Font` 构造的字体没有文件数据
+    data: Vec<Option<Arc<[u8]>>>,
+}
+
+impl std::fmt::Debug for FontChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontChain").field("fonts", &self.fonts.len()).finish()
+    }
+}
+
+impl Default for FontChain {
+    fn default() -> Self {
+        Self::embedded()
+    }
+}
+
+impl FontChain {
+    /// 仅包含内嵌字体的字体链
+    pub fn embedded() -> Self {
+        let (font, data) = embedded_font().clone();
+        FontChain { fonts: vec![font], data: vec![Some(data)] }
+    }
+
+    /// 由给定字体依次组成的字体链，末尾追加内嵌字体
+    pub fn new(fonts: Vec<Font<'static>>) -> Self {
+        let data = vec![None; fonts.len()];
+        FontChain { fonts, data }.with_embedded()
+    }
+
+    fn with_embedded(mut self) -> Self {
+        let (font, data) = embedded_font().clone();
+        self.fonts.push(font);
+        self.data.push(Some(data));
+        self
+    }
+
+    /// 依次加载 TTF/OTF 字体文件（第一个为主字体），末尾追加内嵌字体
+    pub fn load<S: AsRef<str>>(paths: &[S]) -> Result<Self> {
+        let mut chain = FontChain { fonts: Vec::new(), data: Vec::new() };
+        for path in paths {
+            let path = path.as_ref();
+            let data: Arc<[u8]> = std::fs::read(path).map_err(|e| AsciiArtError::FontLoad(format!("{path}: {e}")))?.into();
+            let font = Font::try_from_vec(data.to_vec())
+                .ok_or_else(|| AsciiArtError::FontLoad(format!("{path}: invalid font data")))?;
+            chain.fonts.push(font);
+            chain.data.push(Some(data));
+        }
+        Ok(chain.with_embedded())
+    }
+
+    /// 字体链中的字体数量（包括内嵌字体）
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    /// 第 `index` 个字体的文件数据
+    pub fn font_data(&self, index: usize) -> Option<&[u8]> {
+        self.data.get(index)?.as_deref()
+    }
+
+    /// 主字体，用于确定行高、基线和字符单元格宽度
+    pub fn primary(&self) -> &Font<'static> {
+        &self.fonts[0]
+    }
+
+    /// 字体链中第 `index` 个字体
+    pub fn font_for_index(&self, index: usize) -> Option<&Font<'static>> {
+        self.fonts.get(index)
+    }
+
+    /// 包含该字符的第一个字体，所有字体都不包含时返回 None（空白字符总是使用主字体）
+    pub fn font_for(&self, c: char) -> Option<&Font<'static>> {
+        self.index_for(c).map(|index| &self.fonts[index])
+    }
+
+    /// 包含该字符的第一个字体在字体链中的位置（空白字符总是使用主字体）
+    pub fn index_for(&self, c: char) -> Option<usize> {
+        if c.is_whitespace() {
+            return Some(0);
+        }
+        self.fonts.iter().position(|font| font.glyph(c).id() != GlyphId(0))
+    }
+
+    /// 字体链中是否有字体包含该字符（空白字符总是视为存在）
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.font_for(c).is_some()
+    }
+
+    /// 主字体的字符单元格宽高比（字符前进宽度 / 行高）
+    pub fn cell_aspect_ratio(&self) -> f32 {
+        // rusttype 的字号即行高 (ascent - descent)，与渲染时的单元格高度一致
+        let size = 100.0;
+        let advance = self.primary().glyph('M').scaled(Scale::uniform(size)).h_metrics().advance_width;
+        if advance > 0.0 { advance / size } else { 0.6 }
+    }
+}
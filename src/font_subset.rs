@@ -0,0 +1,272 @@
+use std::collections::BTreeSet;
+
+// 复合字形的标志位
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+/// 字体文件的格式，决定 `@font-face` 中声明的 `format()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontFormat {
+    TrueType,
+    OpenType,
+}
+
+impl FontFormat {
+    /// 按文件头识别单个字体文件，字体集合 (TTC) 等其他格式返回 None
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data.get(0..4)? {
+            [0, 1, 0, 0] | b"true" => Some(FontFormat::TrueType),
+            b"OTTO" => Some(FontFormat::OpenType),
+            _ => None,
+        }
+    }
+
+    pub fn css_format(&self) -> &'static str {
+        match self {
+            FontFormat::TrueType => "truetype",
+            FontFormat::OpenType => "opentype",
+        }
+    }
+}
+
+struct Table<'a> {
+    tag: [u8; 4],
+    data: &'a [u8],
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// 按 4 字节对齐（不足补零）计算的表校验和
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn parse_tables(data: &[u8]) -> Option<Vec<Table<'_>>> {
+    let num_tables = read_u16(data, 4)? as usize;
+    (0..num_tables)
+        .map(|i| {
+            let record = 12 + i * 16;
+            let tag = data.get(record..record + 4)?.try_into().ok()?;
+            let offset = read_u32(data, record + 8)? as usize;
+            let length = read_u32(data, record + 12)? as usize;
+            Some(Table { tag, data: data.get(offset..offset.checked_add(length)?)? })
+        })
+        .collect()
+}
+
+// 复合字形引用的组件字形
+fn composite_components(glyph: &[u8]) -> Vec<u16> {
+    let mut components = Vec::new();
+    if glyph.len() < 10 || (read_u16(glyph, 0).unwrap_or(0) as i16) >= 0 {
+        return components;
+    }
+
+    let mut offset = 10;
+    while let (Some(flags), Some(index)) = (read_u16(glyph, offset), read_u16(glyph, offset + 2)) {
+        components.push(index);
+        offset += 4 + if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            offset += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    components
+}
+
+/// TrueType 字体子集化：生成只包含给定字形（以及 .notdef 和复合字形引用的组件）轮廓的 TrueType 字体
+///
+/// 字形编号和其余表保持不变，未用到的字形轮廓被清空，因此字符映射依然有效。
+/// 不是带 `glyf` 表的 TrueType 字体或数据无效时返回 None。
+pub fn subset_truetype(data: &[u8], glyph_ids: impl IntoIterator<Item = u16>) -> Option<Vec<u8>> {
+    if FontFormat::detect(data)? != FontFormat::TrueType {
+        return None;
+    }
+
+    let tables = parse_tables(data)?;
+    let table = |tag: &[u8; 4]| tables.iter().find(|t| &t.tag == tag).map(|t| t.data);
+    let (head, maxp, loca, glyf) = (table(b"head")?, table(b"maxp")?, table(b"loca")?, table(b"glyf")?);
+
+    let num_glyphs = read_u16(maxp, 4)? as usize;
+    let long_loca = read_u16(head, 50)? != 0;
+    let glyph_range = |id: usize| -> Option<&[u8]> {
+        let (start, end) = if long_loca {
+            (read_u32(loca, id * 4)? as usize, read_u32(loca, id * 4 + 4)? as usize)
+        } else {
+            (read_u16(loca, id * 2)? as usize * 2, read_u16(loca, id * 2 + 2)? as usize * 2)
+        };
+        glyf.get(start..end)
+    };
+
+    // 需要保留的字形，包括复合字形递归引用的组件
+    let mut keep = BTreeSet::new();
+    let mut pending: Vec<u16> = std::iter::once(0).chain(glyph_ids).collect();
+    while let Some(id) = pending.pop() {
+        if (id as usize) < num_glyphs && keep.insert(id) {
+            pending.extend(composite_components(glyph_range(id as usize)?));
+        }
+    }
+
+    // 重建 glyf 和 loca（统一使用长格式偏移）
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    for id in 0..num_glyphs {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if keep.contains(&(id as u16)) {
+            new_glyf.extend_from_slice(glyph_range(id)?);
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let mut new_head = head.to_vec();
+    new_head[8..12].fill(0);
+    new_head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+    // 数字签名在修改后失效，直接去掉
+    let tables: Vec<(&[u8; 4], &[u8])> = tables
+        .iter()
+        .filter(|t| &t.tag != b"DSIG")
+        .map(|t| {
+            let data: &[u8] = match &t.tag {
+                b"head" => &new_head,
+                b"loca" => &new_loca,
+                b"glyf" => &new_glyf,
+                _ => t.data,
+            };
+            (&t.tag, data)
+        })
+        .collect();
+
+    // 写出表目录和各表数据
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.max(1).leading_zeros() as u16;
+    let search_range = 16u16 << entry_selector;
+
+    let mut font = Vec::new();
+    font.extend_from_slice(&read_u32(data, 0)?.to_be_bytes());
+    for value in [num_tables, search_range, entry_selector, num_tables * 16 - search_range] {
+        font.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = 0;
+    for (tag, table) in &tables {
+        if *tag == b"head" {
+            head_offset = offset;
+        }
+        font.extend_from_slice(*tag);
+        font.extend_from_slice(&checksum(table).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        font.extend_from_slice(table);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+    font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    Some(font)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusttype::{point, Font, Scale};
+
+    const DEJAVU: &[u8] = include_bytes!("../res/DejaVuSansMono.ttf");
+
+    // 字体中某个字形在 glyf 表中的数据
+    fn glyph_data(font: &[u8], id: u16) -> &[u8] {
+        let tables = parse_tables(font).unwrap();
+        let table = |tag: &[u8; 4]| tables.iter().find(|t| &t.tag == tag).unwrap().data;
+        let (head, loca, glyf) = (table(b"head"), table(b"loca"), table(b"glyf"));
+        let id = id as usize;
+        let (start, end) = if read_u16(head, 50).unwrap() != 0 {
+            (read_u32(loca, id * 4).unwrap() as usize, read_u32(loca, id * 4 + 4).unwrap() as usize)
+        } else {
+            (read_u16(loca, id * 2).unwrap() as usize * 2, read_u16(loca, id * 2 + 2).unwrap() as usize * 2)
+        };
+        &glyf[start..end]
+    }
+
+    fn has_outline(font: &Font, c: char) -> bool {
+        font.glyph(c).scaled(Scale::uniform(32.0)).positioned(point(0.0, 0.0)).pixel_bounding_box().is_some()
+    }
+
+    #[test]
+    fn keeps_only_requested_glyphs() {
+        let original = Font::try_from_bytes(DEJAVU).unwrap();
+        let ids: Vec<u16> = "#Aé".chars().map(|c| original.glyph(c).id().0).collect();
+
+        // é 是引用其他字形的复合字形
+        let e_acute = glyph_data(DEJAVU, ids[2]);
+        let components = composite_components(e_acute);
+        assert!(!components.is_empty());
+
+        let subset = subset_truetype(DEJAVU, ids.iter().copied()).unwrap();
+        assert!(subset.len() < DEJAVU.len());
+
+        let font = Font::try_from_vec(subset.clone()).unwrap();
+        for c in "#Aé".chars() {
+            assert!(has_outline(&font, c), "{c} lost its outline");
+            // 字形编号保持不变
+            assert_eq!(font.glyph(c).id(), original.glyph(c).id());
+        }
+        for c in "Zq%".chars() {
+            assert!(has_outline(&original, c));
+            assert!(!has_outline(&font, c), "{c} should have been dropped");
+        }
+        for id in components {
+            assert!(!glyph_data(&subset, id).is_empty());
+        }
+    }
+
+    #[test]
+    fn writes_valid_checksums() {
+        let subset = subset_truetype(DEJAVU, [36, 37]).unwrap();
+        // head 表中的 checkSumAdjustment 使整个文件的校验和为固定值
+        assert_eq!(checksum(&subset), 0xB1B0_AFBA);
+
+        for table in parse_tables(&subset).unwrap() {
+            let record = (0..read_u16(&subset, 4).unwrap() as usize)
+                .map(|i| 12 + i * 16)
+                .find(|&r| subset[r..r + 4] == table.tag)
+                .unwrap();
+            // head 表的校验和按 checkSumAdjustment 为 0 计算
+            let mut data = table.data.to_vec();
+            if &table.tag == b"head" {
+                data[8..12].fill(0);
+            }
+            assert_eq!(read_u32(&subset, record + 4).unwrap(), checksum(&data));
+        }
+        assert!(parse_tables(&subset).unwrap().iter().all(|t| &t.tag != b"DSIG"));
+    }
+
+    #[test]
+    fn rejects_non_truetype_data() {
+        assert_eq!(subset_truetype(b"OTTO\0\0\0\0", [1]), None);
+        assert_eq!(subset_truetype(b"not a font", [1]), None);
+        assert_eq!(FontFormat::detect(DEJAVU), Some(FontFormat::TrueType));
+    }
+}
@@ -1,11 +1,13 @@
+use crate::ascii_grid::AsciiGrid;
 use crate::ascii_mapping::{AsciiConfig, AsciiMapper};
-use crate::ascii_to_image::AsciiToImageRenderer;
+use crate::ascii_to_image::{AsciiToImageRenderer, ImageRenderOptions, DEFAULT_GIF_FONT_SIZE};
+use crate::error::Result;
+use crate::preprocessing::Preprocessing;
 use image::codecs::gif::Repeat::Infinite;
 use image::codecs::gif::{GifDecoder, GifEncoder};
 use image::{AnimationDecoder, Delay, Frame, ImageBuffer, Rgba};
 use rayon::iter::IntoParallelIterator;
 use rayon::iter::ParallelIterator;
-use std::error::Error;
 use std::time::Instant;
 use std::{
     fs::File,
@@ -14,16 +16,32 @@ use std::{
     time::Duration,
 };
 
+type RgbaFrame = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
 pub struct GifAsciiHandler {
-    config: AsciiConfig,
+    mapper: AsciiMapper,
+    preprocessing: Preprocessing,
+    render_options: ImageRenderOptions,
 }
 
 impl GifAsciiHandler {
     pub fn new(config: AsciiConfig) -> Self {
-        GifAsciiHandler { config }
+        GifAsciiHandler { mapper: AsciiMapper::new(config), preprocessing: Preprocessing::default(), render_options: ImageRenderOptions::default() }
+    }
+
+    /// 转换前对每一帧执行的预处理步骤
+    pub fn with_preprocessing(mut self, preprocessing: Preprocessing) -> Self {
+        self.preprocessing = preprocessing;
+        self
+    }
+
+    /// 导出 GIF 时渲染字符帧的设置
+    pub fn with_render_options(mut self, render_options: ImageRenderOptions) -> Self {
+        self.render_options = render_options;
+        self
     }
 
-    fn gif_to_ascii(&self, path: &str) -> Result<(Vec<String>, Vec<u64>), Box<dyn Error>> {
+    fn gif_to_ascii(&self, path: &str) -> Result<(Vec<AsciiGrid>, Vec<u64>)> {
         let file = File::open(path)?;
         let buf_reader = BufReader::new(file);
         let decoder = GifDecoder::new(buf_reader)?;
@@ -34,7 +52,8 @@ impl GifAsciiHandler {
         let mut delays = Vec::with_capacity(frames.len());
 
         for frame in frames {
-            let ascii = self.config_to_ascii(&image::DynamicImage::ImageRgba8(frame.clone().into_buffer()))?;
+            let img = self.preprocessing.apply(image::DynamicImage::ImageRgba8(frame.clone().into_buffer()))?;
+            let ascii = self.mapper.image_to_grid(&img)?;
             ascii_frames.push(ascii);
 
             // 提取帧延迟时间
@@ -45,7 +64,7 @@ impl GifAsciiHandler {
         Ok((ascii_frames, delays))
     }
 
-    pub fn play_gif(&self, path: &str, loops: Option<u32>) -> Result<(), Box<dyn Error>> {
+    pub fn play_gif(&self, path: &str, loops: Option<u32>) -> Result<()> {
         let (ascii_frames, delays) = self.gif_to_ascii(path)?;
 
         let stdout = io::stdout();
@@ -59,7 +78,7 @@ impl GifAsciiHandler {
         for _ in loop_count {
             for (ascii, delay) in ascii_frames.clone().into_iter().zip(delays.clone()) {
                 write!(handle, "\x1B[2J\x1B[H")?; // 清屏
-                handle.write_all(ascii.as_bytes())?;
+                handle.write_all(ascii.to_ansi_text(self.mapper.config().color_depth, self.mapper.config().color_tolerance).as_bytes())?;
                 handle.flush()?;
                 sleep(Duration::from_millis(delay));
             }
@@ -67,7 +86,7 @@ impl GifAsciiHandler {
         Ok(())
     }
 
-    pub fn export_to_gif(&self, input_path: &str, output_path: &str) -> Result<(), Box<dyn Error>> {
+    pub fn export_to_gif(&self, input_path: &str, output_path: &str) -> Result<()> {
         let (ascii_frames, delays) = self.gif_to_ascii(input_path)?;
         println!("Total Frames: {}", ascii_frames.len());
 
@@ -78,28 +97,23 @@ impl GifAsciiHandler {
         let timer = Instant::now();
 
         // 并行处理图像
-        let frames_with_index: Vec<(usize, ImageBuffer<Rgba<u8>, Vec<u8>>)> = (0..ascii_frames.len())
+        let frames_with_index: Vec<(usize, RgbaFrame)> = (0..ascii_frames.len())
             .into_par_iter() // 转换为并行迭代器
-            .map(|i| {
-                let img = self.ascii_frame_to_img(&ascii_frames[i]).expect("Failed to convert ASCII frame to image");
-                (i, img)
-            })
-            .collect();
+            .map(|i| self.ascii_frame_to_img(&ascii_frames[i]).map(|img| (i, img)))
+            .collect::<Result<_>>()?;
 
         // 根据原始索引对图像进行排序，确保顺序正确（Rayon库提供的并行迭代器大多数情况下不会改变顺序，但还是排序一下以确保不出错）
-        let mut frames: Vec<ImageBuffer<Rgba<u8>, Vec<u8>>> = vec![ImageBuffer::new(0, 0); frames_with_index.len()];
+        let mut frames: Vec<RgbaFrame> = vec![ImageBuffer::new(0, 0); frames_with_index.len()];
         for (index, frame) in frames_with_index {
             frames[index] = frame;
         }
 
-        let mut count = 1;
         // 编码GIF
-        for (frame, delay) in frames.into_iter().zip(delays) {
+        for (count, (frame, delay)) in frames.into_iter().zip(delays).enumerate() {
             let sub_timer = Instant::now();
             let new_frame = Frame::from_parts(frame, 0, 0, Delay::from_saturating_duration(Duration::from_millis(delay)));
             encoder.encode_frame(new_frame)?;
-            println!("Render Frame {}/{} succeed in {}", count, ascii_frames.len() , format_duration(sub_timer.elapsed()));
-            count += 1;
+            println!("Render Frame {}/{} succeed in {}", count + 1, ascii_frames.len() , format_duration(sub_timer.elapsed()));
         }
 
         println!("Rendering finished in {}", format_duration(timer.elapsed()));
@@ -107,22 +121,10 @@ impl GifAsciiHandler {
         Ok(())
     }
 
-    fn config_to_ascii(&self, img: &image::DynamicImage) -> Result<String, Box<dyn Error>> {
-        let mapper = AsciiMapper::new(self.config.clone());
-        mapper.image_to_ascii(img)
-    }
-
-    fn ascii_frame_to_img(&self, ascii: &str) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Box<dyn Error>> {
-        let mut renderer = AsciiToImageRenderer::new(self.config.clone(), 16)?;
-        let img = renderer.render_ascii_to_image(ascii)?;
-        let mut rgba_img = ImageBuffer::new(img.width(), img.height());
-        for (x, y, pixel) in img.enumerate_pixels() {
-            let rgba_pixel = Rgba([pixel[0], pixel[1], pixel[2], 255]);
-            rgba_img.put_pixel(x, y, rgba_pixel);
-        }
-        let img = rgba_img;
-
-        Ok(img)
+    fn ascii_frame_to_img(&self, grid: &AsciiGrid) -> Result<RgbaFrame> {
+        let renderer = AsciiToImageRenderer::from_options(&self.render_options, DEFAULT_GIF_FONT_SIZE)?
+            .with_fonts(self.mapper.config().font.clone());
+        renderer.render_grid_to_image(grid)
     }
 }
 
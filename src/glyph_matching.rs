@@ -0,0 +1,75 @@
+use crate::custom_charset_util::glyph_coverage_matrix;
+use crate::error::Result;
+use crate::font_chain::FontChain;
+use rusttype::Scale;
+
+/// 每个字符单元格采样的列数
+pub const MATRIX_COLS: usize = 4;
+/// 每个字符单元格采样的行数
+pub const MATRIX_ROWS: usize = 8;
+
+// 整体亮度误差相对于逐点误差的权重
+const TONE_WEIGHT: f32 = 4.0;
+
+/// 将单元格的亮度分布与字符的形状进行匹配
+pub struct GlyphMatcher {
+    glyphs: Vec<(char, Vec<f32>, f32)>,
+}
+
+impl GlyphMatcher {
+    /// 每个字符使用字体链中第一个包含它的字体采样
+    pub fn new(charset: &[char], fonts: &FontChain) -> Result<Self> {
+        let scale = Scale::uniform(32.0);
+
+        let mut glyphs: Vec<(char, Vec<f32>, f32)> = charset
+            .iter()
+            .map(|&c| {
+                let font = fonts.font_for(c).unwrap_or(fonts.primary());
+                let matrix = glyph_coverage_matrix(font, scale, c, MATRIX_COLS, MATRIX_ROWS);
+                let mean = mean(&matrix);
+                (c, matrix, mean)
+            })
+            .collect();
+
+        // 以最密集的字符为基准归一化，使全亮的单元格对应最密集的字符
+        let max_mean = glyphs.iter().map(|g| g.2).fold(0.0f32, f32::max);
+        if max_mean > 0.0 {
+            for (_, matrix, mean) in glyphs.iter_mut() {
+                matrix.iter_mut().for_each(|v| *v /= max_mean);
+                *mean /= max_mean;
+            }
+        }
+
+        Ok(GlyphMatcher { glyphs })
+    }
+
+    /// 选择与单元格亮度分布（按行存储，0.0-1.0，值越大越亮）最接近的字符
+    ///
+    /// 距离由逐点的 L2 误差和整体亮度误差两部分组成，后者保证平坦区域的明暗层次
+    pub fn best_match(&self, pattern: &[f32]) -> char {
+        let pattern_mean = mean(pattern);
+
+        self.glyphs
+            .iter()
+            .map(|(c, matrix, glyph_mean)| {
+                let structure = matrix
+                    .iter()
+                    .zip(pattern)
+                    .map(|(g, p)| (g - p) * (g - p))
+                    .sum::<f32>() / pattern.len() as f32;
+                let tone = (glyph_mean - pattern_mean) * (glyph_mean - pattern_mean);
+                (*c, structure + TONE_WEIGHT * tone)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(c, _)| c)
+            .unwrap_or(' ')
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
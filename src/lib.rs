@@ -0,0 +1,38 @@
+//! 将图像（包括 GIF 动图）转换为 ASCII Art 字符画的库
+
+pub mod ansi_parser;
+pub mod ascii_grid;
+pub mod ascii_mapping;
+pub mod ascii_to_image;
+pub mod block_elements;
+pub mod charset_registry;
+pub mod color_depth;
+pub mod config_file;
+pub mod custom_charset_util;
+pub mod dithering;
+pub mod edge_detection;
+pub mod error;
+pub mod font_chain;
+pub mod font_subset;
+pub mod gif_to_ascii;
+pub mod glyph_matching;
+pub mod luminance;
+pub mod output_handler;
+pub mod preprocessing;
+pub mod resampling;
+pub mod tone_mapping;
+
+pub use crate::ascii_grid::{AsciiCell, AsciiGrid, TextStyle};
+pub use crate::ascii_mapping::{AlphaMode, AsciiConfig, AsciiMapper, Charset, ColorMode, RenderMode};
+pub use crate::ascii_to_image::{AsciiToImageRenderer, ImageRenderOptions, Rasterization};
+pub use crate::charset_registry::{CharsetDefinition, CharsetRegistry};
+pub use crate::color_depth::ColorDepth;
+pub use crate::dithering::DitherMode;
+pub use crate::error::{AsciiArtError, Result};
+pub use crate::font_chain::FontChain;
+pub use crate::gif_to_ascii::GifAsciiHandler;
+pub use crate::luminance::LuminanceModel;
+pub use crate::output_handler::{AsciiArtOutputFormat, OutputHandler, OutputOptions, SvgOptions};
+pub use crate::preprocessing::{PreprocessStep, Preprocessing};
+pub use crate::resampling::ResampleFilter;
+pub use crate::tone_mapping::ToneMapping;
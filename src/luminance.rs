@@ -0,0 +1,72 @@
+use crate::error::AsciiArtError;
+use image::Rgb;
+use std::sync::OnceLock;
+
+/// 由像素颜色计算亮度的模型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LuminanceModel {
+    /// ITU-R BT.601 权重 (0.299, 0.587, 0.114)
+    #[default]
+    Rec601,
+    /// ITU-R BT.709 / sRGB 权重 (0.2126, 0.7152, 0.0722)
+    Rec709,
+    /// CIE 1976 明度 L*，与人眼感知的亮度近似线性
+    CieLightness,
+    /// 取各通道最大值，饱和色不会显得过暗
+    MaxChannel,
+}
+
+impl LuminanceModel {
+    /// 颜色的亮度 (0-255)
+    ///
+    /// 加权求和在线性光空间中进行，结果再编码回感知均匀的 0-255 范围
+    pub fn luminance(&self, color: Rgb<u8>) -> u32 {
+        let [r, g, b] = color.0.map(srgb_u8_to_linear);
+        let value = match self {
+            LuminanceModel::Rec601 => linear_to_srgb(0.299 * r + 0.587 * g + 0.114 * b),
+            LuminanceModel::Rec709 => linear_to_srgb(0.2126 * r + 0.7152 * g + 0.0722 * b),
+            LuminanceModel::CieLightness => {
+                let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                let lightness = if y > 0.008856 { 116.0 * y.cbrt() - 16.0 } else { 903.3 * y };
+                lightness / 100.0
+            }
+            LuminanceModel::MaxChannel => linear_to_srgb(r.max(g).max(b)),
+        };
+        (value.clamp(0.0, 1.0) * 255.0).round() as u32
+    }
+}
+
+impl std::str::FromStr for LuminanceModel {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rec601" | "601" => Ok(LuminanceModel::Rec601),
+            "rec709" | "709" => Ok(LuminanceModel::Rec709),
+            "lstar" | "cie-lstar" | "lightness" => Ok(LuminanceModel::CieLightness),
+            "max" => Ok(LuminanceModel::MaxChannel),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的亮度模型: {s}"))),
+        }
+    }
+}
+
+/// sRGB 编码值 (0.0-1.0) 转换为线性光强度
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// 线性光强度 (0.0-1.0) 转换为 sRGB 编码值
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// 8 位 sRGB 分量对应的线性光强度（查表）
+pub fn srgb_u8_to_linear(c: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))[c as usize]
+}
+
+/// 线性光强度转换为 8 位 sRGB 分量
+pub fn linear_to_srgb_u8(c: f32) -> u8 {
+    (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8
+}
@@ -1,54 +1,64 @@
-mod ascii_mapping;
 mod cli;
-mod custom_charset_util;
-mod output_handler;
-mod gif_to_ascii;
-mod ascii_to_image;
 
-use crate::ascii_mapping::AsciiMapper;
-use crate::cli::parse_args;
-use crate::gif_to_ascii::GifAsciiHandler;
-use crate::output_handler::OutputHandler;
+use ascii_art_generator::{AsciiMapper, GifAsciiHandler, OutputHandler, Result};
+use crate::cli::{parse_args, CliCommand};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = parse_args()?;
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let args = match parse_args()? {
+        CliCommand::Convert(args) => *args,
+        CliCommand::ListCharsets(registry) => {
+            for definition in registry.iter() {
+                println!("{:<10} {:<52} {}", definition.name, definition.description, definition.glyphs);
+            }
+            return Ok(());
+        }
+    };
 
     let config = args.config.clone();
 
-    let img = image::open(&args.input_path)?;
-    
+    let img = args.preprocessing.open(&args.input_path)?;
+
     let img_extension = args.input_path.split(".").last().unwrap();
 
     let mapper = AsciiMapper::new(args.config);
 
-    let ascii_art = mapper.image_to_ascii(&img)?;
-    
+    let ascii_art = mapper.image_to_grid(&img)?;
+
     if img_extension == "gif" {
         if let Some(output_path) = args.output_path {
             if output_path.ends_with(".gif") {
                 // gif输出
                 println!("Exporting to gif...");
-                let handler = GifAsciiHandler::new(config);
+                let handler = GifAsciiHandler::new(config)
+                    .with_preprocessing(args.preprocessing)
+                    .with_render_options(args.output.image);
                 handler.export_to_gif(&args.input_path, &output_path)?;
                 println!("ASCII Art saved to {}", output_path);
             } else {
                 // 常规输出
-                let (handler, final_path)= OutputHandler::from_path(output_path)?;
+                let (handler, final_path)= OutputHandler::from_path(output_path, &args.output)?;
                 handler.save_ascii_art_to_file(&ascii_art, &final_path, &config)?;
+                println!("ASCII Art saved to {}", final_path);
             }
         } else {
             // gif播放
-            let player = GifAsciiHandler::new(config);
+            let player = GifAsciiHandler::new(config).with_preprocessing(args.preprocessing);
             player.play_gif(&args.input_path, None)?;
         }
-    } else { 
-        if let Some(output_path) = args.output_path {
-            let (handler, final_path)= OutputHandler::from_path(output_path)?;
-            handler.save_ascii_art_to_file(&ascii_art, &final_path, &config)?;
-        } else { 
-            println!("{}", ascii_art)
-        }
+    } else if let Some(output_path) = args.output_path {
+        let (handler, final_path)= OutputHandler::from_path(output_path, &args.output)?;
+        handler.save_ascii_art_to_file(&ascii_art, &final_path, &config)?;
+        println!("ASCII Art saved to {}", final_path);
+    } else {
+        println!("{}", ascii_art.to_ansi_text(config.color_depth, config.color_tolerance))
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
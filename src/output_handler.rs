@@ -1,17 +1,19 @@
+use crate::ascii_grid::{AsciiCell, AsciiGrid};
 use crate::ascii_mapping::{AsciiConfig, Charset};
-use crate::ascii_to_image::AsciiToImageRenderer;
-use ansi_to_html::Converter;
-use html_escape::encode_text;
+use crate::ascii_to_image::{AsciiToImageRenderer, ImageRenderOptions, DEFAULT_IMAGE_FONT_SIZE};
+use crate::error::{AsciiArtError, Result};
+use crate::font_chain::FontChain;
+use crate::font_subset::{subset_truetype, FontFormat};
+use html_escape::{encode_double_quoted_attribute, encode_text};
 use image::Rgb;
+use rusttype::Scale;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use std::fs::File;
-use std::io;
 use std::io::Write;
 use std::path::PathBuf;
 
 pub trait AsciiArtOutputFormat {
-    fn write_to(&self, writer: &mut File, ascii_art: &str, config: &AsciiConfig) -> Result<(), Box<dyn Error>>;
+    fn write_to(&self, writer: &mut File, grid: &AsciiGrid, config: &AsciiConfig) -> Result<()>;
     fn file_extension(&self) -> &str;
 }
 
@@ -19,13 +21,46 @@ pub struct TxtFormat;
 pub struct JsonFormat;
 pub struct HtmlFormat;
 
+pub struct SvgFormat {
+    image: ImageRenderOptions,
+    svg: SvgOptions,
+}
+
+/// 输出格式的渲染设置，由 [`OutputHandler::from_path`] 传给需要渲染的格式
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// 图像和 SVG 输出的字号、单元格尺寸、留白和颜色
+    pub image: ImageRenderOptions,
+    /// SVG 输出的字体设置
+    pub svg: SvgOptions,
+}
+
+/// SVG 输出的设置，字号、单元格尺寸、留白和颜色与图像输出共用 [`ImageRenderOptions`](crate::ImageRenderOptions)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    /// 文字使用的 CSS `font-family`
+    pub font_family: String,
+    /// 将用到的字形子集以 `@font-face` 嵌入，使显示结果不依赖本机安装的字体
+    pub embed_font: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            font_family: "'DejaVu Sans Mono', monospace".to_string(),
+            embed_font: false,
+        }
+    }
+}
+
 pub struct ImageFormat {
     extension: String,
+    options: ImageRenderOptions,
 }
 
 impl AsciiArtOutputFormat for TxtFormat {
-    fn write_to(&self, writer: &mut File, ascii_art: &str, ascii_config: &AsciiConfig) -> Result<(), Box<dyn Error>> {
-        writer.write_all(ascii_art.as_bytes())?;
+    fn write_to(&self, writer: &mut File, grid: &AsciiGrid, ascii_config: &AsciiConfig) -> Result<()> {
+        writer.write_all(grid.to_ansi_text(ascii_config.color_depth, ascii_config.color_tolerance).as_bytes())?;
 
         let charset_str = if ascii_config.charset == Charset::CUSTOM {
             &ascii_config.custom_charset
@@ -51,6 +86,50 @@ struct AsciiArtJson {
     version: String,
     config: AsciiConfigJson,
     ascii_art: String,
+    cells: Vec<Vec<AsciiCellJson>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AsciiCellJson {
+    glyph: char,
+    luminance: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    foreground: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<String>,
+    #[serde(default = "opaque", skip_serializing_if = "is_opaque")]
+    alpha: u8,
+    #[serde(default = "single_width", skip_serializing_if = "is_single_width")]
+    width: u32,
+}
+
+fn single_width() -> u32 {
+    1
+}
+
+fn is_single_width(width: &u32) -> bool {
+    *width == 1
+}
+
+fn opaque() -> u8 {
+    255
+}
+
+fn is_opaque(alpha: &u8) -> bool {
+    *alpha == 255
+}
+
+impl From<&AsciiCell> for AsciiCellJson {
+    fn from(cell: &AsciiCell) -> Self {
+        AsciiCellJson {
+            glyph: cell.glyph,
+            luminance: cell.luminance,
+            foreground: cell.foreground.map(to_hex_color),
+            background: cell.background.map(to_hex_color),
+            alpha: cell.alpha,
+            width: cell.display_width(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -64,31 +143,28 @@ struct AsciiConfigJson {
 }
 
 impl AsciiArtOutputFormat for JsonFormat {
-    fn write_to(&self, writer: &mut File, ascii_art: &str, config: &AsciiConfig) -> Result<(), Box<dyn Error>> {
+    fn write_to(&self, writer: &mut File, grid: &AsciiGrid, config: &AsciiConfig) -> Result<()> {
         let charset_str = if config.charset == Charset::CUSTOM {
             config.custom_charset.clone()
         } else {
             config.charset.as_str().to_string()
         };
 
-        let actual_height = if config.height == 0 {
-            count_lines(ascii_art)
-        } else {
-            config.height
-        };
-
         let json_data = AsciiArtJson {
             info: "Generated by ASCII Art Generator".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             config: AsciiConfigJson {
-                width: config.width,
-                height: actual_height,
+                width: grid.width(),
+                height: grid.height(),
                 gamma: config.gamma,
                 charset: charset_str,
                 color_enable: config.color,
                 invert_output: config.invert,
             },
-            ascii_art: ascii_art.to_string()
+            ascii_art: grid.to_plain_text(),
+            cells: grid.rows()
+                .map(|row| row.iter().filter(|c| !c.is_continuation()).map(AsciiCellJson::from).collect())
+                .collect(),
         };
 
         let json_string = serde_json::to_string_pretty(&json_data)?;
@@ -105,29 +181,16 @@ impl AsciiArtOutputFormat for JsonFormat {
 }
 
 impl AsciiArtOutputFormat for HtmlFormat {
-    fn write_to(&self, writer: &mut File, ascii_art: &str, config: &AsciiConfig) -> Result<(), Box<dyn Error>> {
+    fn write_to(&self, writer: &mut File, grid: &AsciiGrid, config: &AsciiConfig) -> Result<()> {
         let charset_str = if config.charset == Charset::CUSTOM {
             &config.custom_charset
         } else {
             config.charset.as_str()
         };
 
-        let actual_height = if config.height == 0 {
-            count_lines(ascii_art)
-        } else {
-            config.height
-        };
-
-        let converter = Converter::new()
-            .skip_escape(!config.color)  // 仅在无颜色时跳过 HTML 转义
-            .skip_optimize(false);
+        let actual_height = grid.height();
 
-        let html_content = if config.color {
-            converter.convert(ascii_art).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
-        } else {
-            // 无颜色时直接转义特殊 HTML 字符
-            encode_text(ascii_art).to_string()
-        };
+        let html_content = grid_to_html(grid);
 
         write!(
             writer,
@@ -170,24 +233,10 @@ impl AsciiArtOutputFormat for HtmlFormat {
             letter-spacing: 0.8px;
             text-align: left; /* 保持 ASCII 艺术内部的左对齐 */
         }}
-        /* 定义 ANSI 颜色变量 */
-        :root {{
-            --black: #000000;
-            --red: #cd0000;
-            --green: #00cd00;
-            --yellow: #cdcd00;
-            --blue: #0000ee;
-            --magenta: #cd00cd;
-            --cyan: #00cdcd;
-            --white: #e5e5e5;
-            --bright-black: #7f7f7f;
-            --bright-red: #ff0000;
-            --bright-green: #00ff00;
-            --bright-yellow: #ffff00;
-            --bright-blue: #5c5cff;
-            --bright-magenta: #ff00ff;
-            --bright-cyan: #00ffff;
-            --bright-white: #ffffff;
+        .wide {{
+            display: inline-block;
+            width: calc(2ch + 1.6px); /* 两列字符宽度加两次字间距 */
+            text-align: center;
         }}
     </style>
 </head>
@@ -209,11 +258,11 @@ impl AsciiArtOutputFormat for HtmlFormat {
 </body>
 </html>
 "#,
-            config.width,
+            grid.width(),
             actual_height,
             env!("CARGO_PKG_VERSION"),
             charset_str,
-            config.width,
+            grid.width(),
             actual_height,
             config.gamma,
             config.color,
@@ -229,31 +278,298 @@ impl AsciiArtOutputFormat for HtmlFormat {
     }
 }
 
-fn count_lines(s: &str) -> u32 {
-    if s.is_empty() {
-        0
+fn to_hex_color(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+// 将字符网格转换为 HTML，颜色相同的相邻单元格合并为同一个 span
+fn grid_to_html(grid: &AsciiGrid) -> String {
+    let mut html = String::new();
+
+    for row in grid.rows() {
+        let mut run = String::new();
+        let mut run_colors = None;
+
+        for cell in row.iter().filter(|c| !c.is_continuation()) {
+            let colors = (cell.foreground, cell.background);
+
+            // 宽字符单独放入固定为两列宽的元素中，避免字体的全角宽度与两个字符宽度不一致导致错位
+            if cell.display_width() == 2 {
+                if let Some(colors) = run_colors.take() {
+                    push_html_span(&mut html, &run, colors, "");
+                }
+                run.clear();
+                push_html_span(&mut html, &cell.glyph.to_string(), colors, "wide");
+                continue;
+            }
+
+            if run_colors != Some(colors) {
+                if let Some(colors) = run_colors {
+                    push_html_span(&mut html, &run, colors, "");
+                }
+                run.clear();
+                run_colors = Some(colors);
+            }
+            run.push(cell.glyph);
+        }
+        if let Some(colors) = run_colors {
+            push_html_span(&mut html, &run, colors, "");
+        }
+        html.push('\n');
+    }
+
+    html
+}
+
+fn push_html_span(html: &mut String, text: &str, colors: (Option<Rgb<u8>>, Option<Rgb<u8>>), class: &str) {
+    let mut style = String::new();
+    if let Some(fg) = colors.0 {
+        style.push_str(&format!("color:{};", to_hex_color(fg)));
+    }
+    if let Some(bg) = colors.1 {
+        style.push_str(&format!("background-color:{};", to_hex_color(bg)));
+    }
+
+    let class = if class.is_empty() { String::new() } else { format!(" class=\"{}\"", class) };
+    if style.is_empty() && class.is_empty() {
+        html.push_str(&encode_text(text));
+    } else if style.is_empty() {
+        html.push_str(&format!("<span{}>{}</span>", class, encode_text(text)));
     } else {
-        s.chars().filter(|&c| c == '\n').count() as u32
+        html.push_str(&format!("<span{} style=\"{}\">{}</span>", class, style, encode_text(text)));
     }
 }
 
-impl AsciiArtOutputFormat for ImageFormat {
-    fn write_to(&self, writer: &mut File, ascii_art: &str, config: &AsciiConfig) -> Result<(), Box<dyn Error>> {
-        let mut renderer = AsciiToImageRenderer::new(config.clone(), 32)?
-            .with_colors(
-                Rgb([0x0C, 0x0C, 0x0C]),
-                Rgb([0xCC, 0xCC, 0xCC])
-            );
-
-        let img = renderer.render_ascii_to_image(ascii_art);
-
-        let format = match self.file_extension() {
-            "png" => image::ImageFormat::Png,
-            "jpg" | "jpeg" => image::ImageFormat::Jpeg,
-            _ => image::ImageFormat::Png,
+impl ImageFormat {
+    // 将带透明度的图像叠加到背景色上
+    fn flatten(img: &image::DynamicImage, background: Rgb<u8>) -> image::DynamicImage {
+        let rgba = img.to_rgba8();
+        let flattened = image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            let p = rgba.get_pixel(x, y);
+            let alpha = p[3] as f32 / 255.0;
+            Rgb([0, 1, 2].map(|c| (p[c] as f32 * alpha + background[c] as f32 * (1.0 - alpha)).round() as u8))
+        });
+        image::DynamicImage::ImageRgb8(flattened)
+    }
+}
+
+impl AsciiArtOutputFormat for SvgFormat {
+    fn write_to(&self, writer: &mut File, grid: &AsciiGrid, config: &AsciiConfig) -> Result<()> {
+        let options = &self.image;
+        let renderer = AsciiToImageRenderer::from_options(options, DEFAULT_IMAGE_FONT_SIZE)?
+            .with_fonts(config.font.clone());
+        let (cell_width, line_height) = renderer.cell_size();
+        let font_size = options.font_size.unwrap_or(DEFAULT_IMAGE_FONT_SIZE);
+        let padding = options.padding;
+        let width = grid.width() * cell_width + 2 * padding;
+        let height = grid.height() * line_height + 2 * padding;
+
+        // 字形的行高 (ascent - descent) 在单元格中垂直居中，与图像输出一致
+        let v_metrics = config.font.primary().v_metrics(Scale::uniform(font_size as f32));
+        let baseline = (line_height as f32 - (v_metrics.ascent - v_metrics.descent)) / 2.0 + v_metrics.ascent;
+
+        let mut font_family = self.svg.font_family.clone();
+        let mut font_faces = String::new();
+        if self.svg.embed_font {
+            let (faces, families) = svg_font_faces(grid, &config.font);
+            font_faces = faces;
+            font_family = families.into_iter().chain(std::iter::once(font_family)).collect::<Vec<_>>().join(", ");
+        }
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        )?;
+        writeln!(
+            writer,
+            "<style><![CDATA[{font_faces}text {{ font-family: {font_family}; font-size: {font_size}px; white-space: pre; }}]]></style>"
+        )?;
+
+        write!(writer, "{}", svg_backgrounds(grid, options, cell_width, line_height, width, height))?;
+
+        // 每行一个 text 元素，颜色相同的相邻字符合并为一个 tspan
+        let foreground = to_hex_color(options.foreground);
+        for (y, row) in grid.rows().enumerate() {
+            let runs = svg_text_runs(row);
+            if runs.iter().all(|(_, _, text)| text.trim().is_empty()) {
+                continue;
+            }
+
+            let text_y = (padding as f32 + y as f32 * line_height as f32 + baseline).round();
+            match runs.as_slice() {
+                // 单色且没有宽字符的行直接输出为一个 text 元素
+                [(0, None, text)] => writeln!(
+                    writer,
+                    r#"<text x="{}" y="{}" fill="{}" xml:space="preserve">{}</text>"#,
+                    padding, text_y, foreground, svg_escape(text)
+                )?,
+                _ => {
+                    write!(writer, r#"<text y="{}" fill="{}" xml:space="preserve">"#, text_y, foreground)?;
+                    for (column, color, text) in &runs {
+                        let x = padding + *column as u32 * cell_width;
+                        let fill = color.map(|c| format!(r#" fill="{}""#, to_hex_color(c))).unwrap_or_default();
+                        write!(writer, r#"<tspan x="{}"{}>{}</tspan>"#, x, fill, svg_escape(text))?;
+                    }
+                    writeln!(writer, "</text>")?;
+                }
+            }
+        }
+
+        writeln!(writer, "</svg>")?;
+        Ok(())
+    }
+
+    fn file_extension(&self) -> &str {
+        "svg"
+    }
+}
+
+// XML 文本转义，引号也一并转义，使字符画中的任意字符都不会破坏文档结构
+fn svg_escape(text: &str) -> std::borrow::Cow<'_, str> {
+    encode_double_quoted_attribute(text)
+}
+
+// 一行中的文字片段（起始列、前景色、文字），前景色相同的相邻字符合并，宽字符单独成段以保证列对齐
+fn svg_text_runs(row: &[AsciiCell]) -> Vec<(usize, Option<Rgb<u8>>, String)> {
+    let mut runs: Vec<(usize, Option<Rgb<u8>>, String)> = Vec::new();
+    let mut wide_before = false;
+
+    for (column, cell) in row.iter().enumerate().filter(|(_, c)| !c.is_continuation()) {
+        let wide = cell.display_width() == 2;
+        match runs.last_mut() {
+            Some((_, color, text)) if *color == cell.foreground && !wide && !wide_before => text.push(cell.glyph),
+            _ => runs.push((column, cell.foreground, cell.glyph.to_string())),
+        }
+        wide_before = wide;
+    }
+
+    runs
+}
+
+// 背景：没有透明单元格时先铺满背景色，再为设置了背景色的单元格绘制矩形；
+// 否则逐个单元格绘制背景，透明度与图像输出一致
+fn svg_backgrounds(grid: &AsciiGrid, options: &ImageRenderOptions, cell_width: u32, line_height: u32, width: u32, height: u32) -> String {
+    let mut svg = String::new();
+    let padding = options.padding;
+    let background = to_hex_color(options.background);
+    let opaque = !options.transparent_background && grid.rows().all(|row| row.iter().all(|c| c.alpha == 255));
+
+    if opaque {
+        svg.push_str(&format!("<rect width=\"100%\" height=\"100%\" fill=\"{background}\"/>\n"));
+    } else if !options.transparent_background && padding > 0 {
+        // 只填充四周的留白
+        let (inner_width, inner_height) = (width - 2 * padding, height - 2 * padding);
+        svg.push_str(&format!(
+            "<path fill-rule=\"evenodd\" fill=\"{background}\" d=\"M0 0h{width}v{height}h-{width}z M{padding} {padding}v{inner_height}h{inner_width}v-{inner_height}z\"/>\n"
+        ));
+    }
+
+    for (y, row) in grid.rows().enumerate() {
+        // 合并颜色和不透明度相同的相邻单元格
+        let mut runs: Vec<(usize, usize, Rgb<u8>, u8)> = Vec::new();
+        for (x, cell) in row.iter().enumerate() {
+            let fill = match cell.background {
+                Some(color) => (color, cell.alpha),
+                None if opaque => continue,
+                None if options.transparent_background => continue,
+                None => (options.background, cell.alpha),
+            };
+            if fill.1 == 0 {
+                continue;
+            }
+
+            match runs.last_mut() {
+                Some((start, len, color, alpha)) if *start + *len == x && (*color, *alpha) == fill => *len += 1,
+                _ => runs.push((x, 1, fill.0, fill.1)),
+            }
+        }
+
+        for (x, len, color, alpha) in runs {
+            let opacity = if alpha < 255 { format!(" fill-opacity=\"{:.3}\"", alpha as f32 / 255.0) } else { String::new() };
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"{}/>\n",
+                padding + x as u32 * cell_width,
+                padding + y as u32 * line_height,
+                len as u32 * cell_width,
+                line_height,
+                to_hex_color(color),
+                opacity
+            ));
+        }
+    }
+
+    svg
+}
+
+// 为字体链中用到的每个字体生成 `@font-face` 规则，返回 CSS 及对应的字体名称（按字体链顺序）
+// TrueType 字体只保留用到的字形，其他格式的字体整体嵌入，无法识别的字体不嵌入
+fn svg_font_faces(grid: &AsciiGrid, fonts: &FontChain) -> (String, Vec<String>) {
+    let glyphs: Vec<char> = grid.rows().flatten().filter(|c| !c.is_continuation()).map(|c| c.glyph).collect();
+
+    let mut css = String::new();
+    let mut families = Vec::new();
+    for index in 0..fonts.len() {
+        let used: Vec<char> = glyphs.iter().copied().filter(|&c| fonts.index_for(c) == Some(index)).collect();
+        let (Some(data), Some(font)) = (fonts.font_data(index), fonts.font_for_index(index)) else {
+            continue;
+        };
+        if used.is_empty() {
+            continue;
+        }
+
+        let Some(format) = FontFormat::detect(data) else {
+            continue;
         };
+        let embedded = match format {
+            FontFormat::TrueType => subset_truetype(data, used.iter().map(|&c| font.glyph(c).id().0))
+                .unwrap_or_else(|| data.to_vec()),
+            FontFormat::OpenType => data.to_vec(),
+        };
+
+        let family = format!("ascii-art-font-{index}");
+        css.push_str(&format!(
+            "@font-face {{ font-family: '{family}'; src: url(data:font/{};base64,{}) format('{}'); }} ",
+            if format == FontFormat::TrueType { "ttf" } else { "otf" },
+            base64_encode(&embedded),
+            format.css_format()
+        ));
+        families.push(format!("'{family}'"));
+    }
+
+    (css, families)
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
 
-        img?.write_to(writer, format)?;
+impl AsciiArtOutputFormat for ImageFormat {
+    fn write_to(&self, writer: &mut File, grid: &AsciiGrid, config: &AsciiConfig) -> Result<()> {
+        let renderer = AsciiToImageRenderer::from_options(&self.options, DEFAULT_IMAGE_FONT_SIZE)?
+            .with_fonts(config.font.clone());
+
+        let img = image::DynamicImage::ImageRgba8(renderer.render_grid_to_image(grid)?);
+
+        match self.file_extension() {
+            // JPEG 不支持透明度，透明区域按背景色填充
+            "jpg" | "jpeg" => Self::flatten(&img, self.options.background).write_to(writer, image::ImageFormat::Jpeg)?,
+            _ => img.write_to(writer, image::ImageFormat::Png)?,
+        }
 
         Ok(())
     }
@@ -277,7 +593,7 @@ impl OutputHandler {
         Self {format}
     }
 
-    pub fn from_path(mut output_path: String) -> Result<(Self, String), Box<dyn Error>> {
+    pub fn from_path(mut output_path: String, options: &OutputOptions) -> Result<(Self, String)> {
         let mut path = PathBuf::from(&output_path);
 
         // 检查是否有扩展名
@@ -295,20 +611,147 @@ impl OutputHandler {
                 Some("txt") => Box::new(TxtFormat) as Box<dyn AsciiArtOutputFormat>,
                 Some("json") => Box::new(JsonFormat) as Box<dyn AsciiArtOutputFormat>,
                 Some("html") => Box::new(HtmlFormat) as Box<dyn AsciiArtOutputFormat>,
-                Some("png" | "jpg" | "jpeg") => Box::new(ImageFormat {extension: path.extension().and_then(|ext| ext.to_str()).unwrap().to_string() }),
-                Some(ext) => return Err(format!("Unsupported file extension: .{}", ext).into()),
-                None => return Err("Failed to parse file extension".into()),
+                Some("svg") => Box::new(SvgFormat { image: options.image.clone(), svg: options.svg.clone() }) as Box<dyn AsciiArtOutputFormat>,
+                Some("png" | "jpg" | "jpeg") => Box::new(ImageFormat {
+                    extension: path.extension().and_then(|ext| ext.to_str()).unwrap().to_string(),
+                    options: options.image.clone(),
+                }),
+                Some(ext) => return Err(AsciiArtError::UnsupportedExtension(ext.to_string())),
+                None => return Err(AsciiArtError::UnsupportedExtension(path.extension().unwrap().to_string_lossy().into())),
             }
         };
 
         Ok((Self::new(format), output_path))
     }
 
-    pub fn save_ascii_art_to_file(&self, ascii_art: &str, output_path: &str, ascii_config: &AsciiConfig) -> Result<(), Box<dyn Error>> {
+    pub fn save_ascii_art_to_file(&self, grid: &AsciiGrid, output_path: &str, ascii_config: &AsciiConfig) -> Result<()> {
         let mut file = File::create(output_path)?;
-        self.format.write_to(&mut file, ascii_art, ascii_config)?;
+        self.format.write_to(&mut file, grid, ascii_config)
 
-        println!("ASCII Art saved to {}", output_path);
-        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 字符、前景色、背景色
+    type TestCell = (char, Option<[u8; 3]>, Option<[u8; 3]>);
+
+    fn grid_from(rows: &[&[TestCell]]) -> AsciiGrid {
+        let mut grid = AsciiGrid::new(rows[0].len() as u32, rows.len() as u32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &(glyph, fg, bg)) in row.iter().enumerate() {
+                let cell = AsciiCell { foreground: fg.map(Rgb), background: bg.map(Rgb), ..AsciiCell::new(glyph) };
+                grid.set(x as u32, y as u32, cell);
+            }
+        }
+        grid
+    }
+
+    // 以固定的 10x20 单元格、无留白输出 SVG 并读回
+    fn render_svg(name: &str, grid: &AsciiGrid, transparent_background: bool) -> String {
+        let image = ImageRenderOptions {
+            cell_width: Some(10),
+            line_height: Some(20),
+            padding: 0,
+            background: Rgb([1, 2, 3]),
+            transparent_background,
+            ..ImageRenderOptions::default()
+        };
+        let options = OutputOptions { image, ..OutputOptions::default() };
+        let path = std::env::temp_dir().join(format!("ascii_art_{}_{name}.svg", std::process::id()));
+        let (handler, path) = OutputHandler::from_path(path.to_string_lossy().into(), &options).unwrap();
+        handler.save_ascii_art_to_file(grid, &path, &AsciiConfig::default()).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        svg
+    }
+
+    #[test]
+    fn svg_extension_selects_svg_format() {
+        let (handler, path) = OutputHandler::from_path("x.svg".to_string(), &OutputOptions::default()).unwrap();
+        assert_eq!(path, "x.svg");
+        assert_eq!(handler.format.file_extension(), "svg");
+    }
+
+    #[test]
+    fn svg_writes_one_text_per_row() {
+        let row: &[_] = &[('a', None, None), ('b', None, None), ('c', None, None)];
+        let svg = render_svg("rows", &grid_from(&[row, row, row]), false);
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<text ").count(), 3);
+        assert_eq!(svg.matches("</text>").count(), 3);
+        assert_eq!(svg.matches(">abc</text>").count(), 3);
+        assert!(!svg.contains("<tspan"));
+    }
+
+    #[test]
+    fn svg_splits_color_runs_into_tspans() {
+        let (red, blue) = (Some([255, 0, 0]), Some([0, 0, 255]));
+        let row: &[_] = &[('a', red, None), ('b', red, None), ('c', blue, None), ('d', blue, None)];
+        let svg = render_svg("tspans", &grid_from(&[row]), false);
+        assert_eq!(svg.matches("<text ").count(), 1);
+        assert_eq!(svg.matches("<tspan").count(), 2);
+        assert!(svg.contains(r##"<tspan x="0" fill="#ff0000">ab</tspan>"##));
+        assert!(svg.contains(r##"<tspan x="20" fill="#0000ff">cd</tspan>"##));
+    }
+
+    #[test]
+    fn svg_escapes_xml_glyphs() {
+        let row: &[_] = &[('<', None, None), ('&', None, None), ('"', None, None), ('>', None, None)];
+        let svg = render_svg("escape", &grid_from(&[row]), false);
+        assert!(svg.contains(">&lt;&amp;&quot;&gt;</text>"), "{svg}");
+        assert!(!svg.contains("<&"));
+    }
+
+    #[test]
+    fn svg_draws_rects_for_cell_backgrounds() {
+        let green = Some([0, 255, 0]);
+        let row: &[_] = &[('a', None, None), ('b', None, green), ('c', None, green), ('d', None, None)];
+        let svg = render_svg("backgrounds", &grid_from(&[row]), false);
+        assert!(svg.contains(r##"<rect width="100%" height="100%" fill="#010203"/>"##));
+        // 相邻的同色背景合并为一个矩形
+        assert!(svg.contains(r##"<rect x="10" y="0" width="20" height="20" fill="#00ff00"/>"##));
+        assert_eq!(svg.matches("<rect").count(), 2);
+    }
+
+    #[test]
+    fn svg_transparent_background_has_no_fill() {
+        let green = Some([0, 255, 0]);
+        let row: &[_] = &[('a', None, None), ('b', None, green)];
+        let svg = render_svg("transparent", &grid_from(&[row]), true);
+        assert!(!svg.contains("100%"));
+        assert!(!svg.contains("<path"));
+        assert!(!svg.contains("#010203"));
+        // 单元格自身的背景色依然绘制
+        assert_eq!(svg.matches("<rect").count(), 1);
+        assert!(svg.contains(r##"<rect x="10" y="0" width="10" height="20" fill="#00ff00"/>"##));
+    }
+
+    #[test]
+    fn base64_matches_rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, expected) in vectors {
+            assert_eq!(base64_encode(input.as_bytes()), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn base64_encodes_all_byte_values() {
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = base64_encode(&data);
+        assert_eq!(encoded.len(), 344);
+        assert!(encoded.starts_with("AAECAwQFBgcICQoL"));
+        assert!(encoded.ends_with("/P3+/w=="));
     }
 }
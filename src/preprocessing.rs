@@ -0,0 +1,114 @@
+use crate::config_file::load_config;
+use crate::error::{AsciiArtError, Result};
+use image::{DynamicImage, ImageDecoder, ImageReader, Rgba};
+use serde::Deserialize;
+
+/// 单个预处理步骤
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+pub enum PreprocessStep {
+    /// 裁剪出左上角位于 (x, y) 的矩形区域
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+    /// 顺时针旋转 90、180 或 270 度
+    Rotate { degrees: u32 },
+    /// 水平翻转
+    FlipHorizontal,
+    /// 垂直翻转
+    FlipVertical,
+    /// 调整亮度，`value` 为加到每个通道上的值 (-255 到 255)
+    Brightness { value: i32 },
+    /// 调整对比度，`value` 为百分比，正值增强、负值减弱
+    Contrast { value: f32 },
+    /// 调整饱和度，`factor` 为 1.0 时不变，0.0 时为灰度
+    Saturation { factor: f32 },
+    /// 高斯模糊
+    Blur { sigma: f32 },
+    /// 反锐化掩模，亮度差小于 `threshold` 的像素不做锐化
+    Sharpen {
+        sigma: f32,
+        #[serde(default)]
+        threshold: i32,
+    },
+}
+
+impl PreprocessStep {
+    pub fn apply(&self, img: DynamicImage) -> Result<DynamicImage> {
+        Ok(match *self {
+            PreprocessStep::Crop { x, y, width, height } => {
+                let in_bounds = x.checked_add(width).is_some_and(|r| r <= img.width())
+                    && y.checked_add(height).is_some_and(|b| b <= img.height());
+                if width == 0 || height == 0 || !in_bounds {
+                    return Err(AsciiArtError::InvalidArgument(format!(
+                        "裁剪区域 {width}x{height}+{x}+{y} 超出图像范围 {}x{}",
+                        img.width(),
+                        img.height()
+                    )));
+                }
+                img.crop_imm(x, y, width, height)
+            }
+            PreprocessStep::Rotate { degrees } => match degrees % 360 {
+                0 => img,
+                90 => img.rotate90(),
+                180 => img.rotate180(),
+                270 => img.rotate270(),
+                _ => return Err(AsciiArtError::InvalidArgument(format!("不支持的旋转角度: {degrees}"))),
+            },
+            PreprocessStep::FlipHorizontal => img.fliph(),
+            PreprocessStep::FlipVertical => img.flipv(),
+            PreprocessStep::Brightness { value } => img.brighten(value),
+            PreprocessStep::Contrast { value } => img.adjust_contrast(value),
+            PreprocessStep::Saturation { factor } => saturate(img, factor),
+            PreprocessStep::Blur { sigma } => img.blur(sigma),
+            PreprocessStep::Sharpen { sigma, threshold } => img.unsharpen(sigma, threshold),
+        })
+    }
+}
+
+/// 转换前对源图像进行的预处理，按顺序依次执行各步骤
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Preprocessing {
+    /// 按 EXIF 方向信息自动旋转/翻转图像
+    pub auto_orient: bool,
+    pub steps: Vec<PreprocessStep>,
+}
+
+impl Preprocessing {
+    /// 从 TOML 或 JSON（按扩展名区分）配置文件读取预处理步骤
+    pub fn from_file(path: &str) -> Result<Self> {
+        load_config(path, |e| AsciiArtError::InvalidArgument(format!("无效的预处理配置 {path}: {e}")))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.auto_orient && self.steps.is_empty()
+    }
+
+    /// 打开图像文件，需要时按 EXIF 方向信息校正后执行预处理步骤
+    pub fn open(&self, path: &str) -> Result<DynamicImage> {
+        let mut decoder = ImageReader::open(path)?.with_guessed_format()?.into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut img = DynamicImage::from_decoder(decoder)?;
+
+        if self.auto_orient {
+            img.apply_orientation(orientation);
+        }
+        self.apply(img)
+    }
+
+    /// 依次执行所有预处理步骤（不含方向校正）
+    pub fn apply(&self, img: DynamicImage) -> Result<DynamicImage> {
+        self.steps.iter().try_fold(img, |img, step| step.apply(img))
+    }
+}
+
+// 将每个像素的颜色向其亮度按 `factor` 外推或内插
+fn saturate(img: DynamicImage, factor: f32) -> DynamicImage {
+    let mut rgba = img.into_rgba8();
+    for Rgba(p) in rgba.pixels_mut() {
+        let luma = 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32;
+        for c in &mut p[..3] {
+            *c = (luma + (*c as f32 - luma) * factor).clamp(0.0, 255.0).round() as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
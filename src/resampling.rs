@@ -0,0 +1,160 @@
+use crate::error::AsciiArtError;
+use crate::luminance::{linear_to_srgb_u8, srgb_u8_to_linear};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+
+/// 将图像缩放到字符网格分辨率时使用的滤波器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleFilter {
+    /// 按面积加权平均，每个输出像素取其覆盖区域内所有像素的加权平均值
+    #[default]
+    Box,
+    /// 最近邻采样
+    Nearest,
+    /// 双线性插值
+    Bilinear,
+    /// Lanczos (a=3) 插值
+    Lanczos,
+}
+
+impl std::str::FromStr for ResampleFilter {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "box" | "area" => Ok(ResampleFilter::Box),
+            "nearest" => Ok(ResampleFilter::Nearest),
+            "bilinear" | "triangle" => Ok(ResampleFilter::Bilinear),
+            "lanczos" | "lanczos3" => Ok(ResampleFilter::Lanczos),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的缩放滤波器: {s}"))),
+        }
+    }
+}
+
+/// 将图像缩放到 `width`x`height`，同时支持放大和缩小
+///
+/// 缩放在线性光空间中进行，并预先乘以透明度，使半透明像素按其不透明度加权
+pub fn resample(img: &DynamicImage, width: u32, height: u32, filter: ResampleFilter) -> RgbaImage {
+    let rgba = img.to_rgba8();
+    let (width, height) = (width.max(1), height.max(1));
+
+    let src = Rgba32FImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let p = rgba.get_pixel(x, y);
+        let a = p[3] as f32 / 255.0;
+        Rgba([srgb_u8_to_linear(p[0]) * a, srgb_u8_to_linear(p[1]) * a, srgb_u8_to_linear(p[2]) * a, a])
+    });
+
+    let resized = match filter {
+        ResampleFilter::Box => area_average(&src, width, height),
+        ResampleFilter::Nearest => imageops::resize(&src, width, height, FilterType::Nearest),
+        ResampleFilter::Bilinear => imageops::resize(&src, width, height, FilterType::Triangle),
+        ResampleFilter::Lanczos => imageops::resize(&src, width, height, FilterType::Lanczos3),
+    };
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let p = resized.get_pixel(x, y);
+        let a = p[3].clamp(0.0, 1.0);
+        let unpremultiply = |c: f32| linear_to_srgb_u8(if a > 0.0 { c / a } else { 0.0 });
+        Rgba([unpremultiply(p[0]), unpremultiply(p[1]), unpremultiply(p[2]), (a * 255.0).round() as u8])
+    })
+}
+
+// 按输出像素覆盖的源像素面积加权平均，覆盖不足一个像素时（放大）退化为取对应像素
+fn area_average(src: &Rgba32FImage, width: u32, height: u32) -> Rgba32FImage {
+    let (src_w, src_h) = src.dimensions();
+    let mut dst = Rgba32FImage::new(width, height);
+    if src_w == 0 || src_h == 0 {
+        return dst;
+    }
+
+    let scale_x = src_w as f64 / width as f64;
+    let scale_y = src_h as f64 / height as f64;
+
+    for y in 0..height {
+        let (y0, y1) = (y as f64 * scale_y, (y + 1) as f64 * scale_y);
+        for x in 0..width {
+            let (x0, x1) = (x as f64 * scale_x, (x + 1) as f64 * scale_x);
+
+            let mut total = [0.0f64; 4];
+            let mut total_weight = 0.0;
+
+            for sy in y0.floor() as u32..(y1.ceil() as u32).min(src_h) {
+                let weight_y = (y1.min(sy as f64 + 1.0) - y0.max(sy as f64)).max(0.0);
+                for sx in x0.floor() as u32..(x1.ceil() as u32).min(src_w) {
+                    let weight = weight_y * (x1.min(sx as f64 + 1.0) - x0.max(sx as f64)).max(0.0);
+                    let p = src.get_pixel(sx, sy);
+                    for (t, &v) in total.iter_mut().zip(p.0.iter()) {
+                        *t += v as f64 * weight;
+                    }
+                    total_weight += weight;
+                }
+            }
+
+            if total_weight > 0.0 {
+                dst.put_pixel(x, y, Rgba(total.map(|t| (t / total_weight) as f32)));
+            }
+        }
+    }
+
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [ResampleFilter; 4] =
+        [ResampleFilter::Box, ResampleFilter::Nearest, ResampleFilter::Bilinear, ResampleFilter::Lanczos];
+
+    fn flat_image(width: u32, height: u32, color: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, color))
+    }
+
+    fn assert_flat(img: &RgbaImage, color: Rgba<u8>) {
+        for p in img.pixels() {
+            for c in 0..4 {
+                assert!(p[c].abs_diff(color[c]) <= 1, "expected {color:?}, got {p:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn upscales_tiny_image_to_width_80() {
+        let color = Rgba([200, 80, 30, 255]);
+        let img = flat_image(10, 10, color);
+        for filter in FILTERS {
+            let out = resample(&img, 80, 40, filter);
+            assert_eq!(out.dimensions(), (80, 40), "{filter:?}");
+            assert_flat(&out, color);
+        }
+    }
+
+    #[test]
+    fn downscales_large_image() {
+        let color = Rgba([12, 150, 240, 128]);
+        let img = flat_image(1000, 700, color);
+        for filter in FILTERS {
+            let out = resample(&img, 80, 28, filter);
+            assert_eq!(out.dimensions(), (80, 28), "{filter:?}");
+            assert_flat(&out, color);
+        }
+    }
+
+    #[test]
+    fn box_filter_averages_in_linear_light() {
+        // 黑白棋盘格的面积平均在线性光空间中为 0.5，编码后约为 188 而不是 128
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 8, |x, y| {
+            if (x + y) % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+        }));
+        let out = resample(&img, 2, 2, ResampleFilter::Box);
+        let midpoint = linear_to_srgb_u8(0.5);
+        assert_flat(&out, Rgba([midpoint, midpoint, midpoint, 255]));
+        assert!(midpoint > 180);
+    }
+
+    #[test]
+    fn zero_target_size_is_clamped() {
+        let out = resample(&flat_image(10, 10, Rgba([1, 2, 3, 255])), 0, 0, ResampleFilter::Box);
+        assert_eq!(out.dimensions(), (1, 1));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::AsciiArtError;
+
+const BINS: usize = 256;
+
+/// 映射到字符前对亮度分布进行的调整，在伽马校正之前进行
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ToneMapping {
+    /// 不调整
+    #[default]
+    None,
+    /// 自动色阶：去掉最暗和最亮各 `clip` 百分比的像素后将剩余范围拉伸到 0-255
+    AutoLevels { clip: f32 },
+    /// 全局直方图均衡化
+    Equalize,
+    /// 限制对比度的自适应直方图均衡化 (CLAHE)，图像划分为 `tiles` x `tiles` 个区块
+    Clahe { clip_limit: f32, tiles: u32 },
+}
+
+impl ToneMapping {
+    pub const DEFAULT_LEVELS_CLIP: f32 = 0.5;
+    pub const DEFAULT_CLAHE_CLIP_LIMIT: f32 = 2.0;
+    pub const DEFAULT_CLAHE_TILES: u32 = 8;
+
+    /// 调整 `width`x`height` 亮度图 (0-255)，值为 None 的像素（完全透明）不参与统计也不修改
+    pub fn apply(&self, luminance: &mut [Option<u32>], width: u32, height: u32) {
+        match *self {
+            ToneMapping::None => {}
+            ToneMapping::AutoLevels { clip } => auto_levels(luminance, clip),
+            ToneMapping::Equalize => {
+                let lut = equalization_lut(&histogram(luminance.iter()), None);
+                for v in luminance.iter_mut().flatten() {
+                    *v = lut[(*v).min(255) as usize] as u32;
+                }
+            }
+            ToneMapping::Clahe { clip_limit, tiles } => clahe(luminance, width, height, clip_limit, tiles),
+        }
+    }
+}
+
+impl std::str::FromStr for ToneMapping {
+    type Err = AsciiArtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(ToneMapping::None),
+            "auto-levels" | "levels" => Ok(ToneMapping::AutoLevels { clip: Self::DEFAULT_LEVELS_CLIP }),
+            "equalize" | "histogram" => Ok(ToneMapping::Equalize),
+            "clahe" => Ok(ToneMapping::Clahe {
+                clip_limit: Self::DEFAULT_CLAHE_CLIP_LIMIT,
+                tiles: Self::DEFAULT_CLAHE_TILES,
+            }),
+            _ => Err(AsciiArtError::InvalidArgument(format!("不支持的色调调整方式: {s}"))),
+        }
+    }
+}
+
+fn histogram<'a>(values: impl Iterator<Item = &'a Option<u32>>) -> [u32; BINS] {
+    let mut hist = [0u32; BINS];
+    for v in values.flatten() {
+        hist[(*v).min(255) as usize] += 1;
+    }
+    hist
+}
+
+fn auto_levels(luminance: &mut [Option<u32>], clip: f32) {
+    let hist = histogram(luminance.iter());
+    let total: u32 = hist.iter().sum();
+    if total == 0 {
+        return;
+    }
+
+    // 累计像素数首次超过裁剪数量的亮度即为黑点/白点
+    let clipped = (total as f32 * clip.clamp(0.0, 50.0) / 100.0) as u32;
+    let low = clip_point(hist.iter().enumerate(), clipped);
+    let high = clip_point(hist.iter().enumerate().rev(), clipped);
+    if high <= low {
+        return;
+    }
+
+    for v in luminance.iter_mut().flatten() {
+        *v = ((*v as f32 - low) / (high - low) * 255.0).clamp(0.0, 255.0).round() as u32;
+    }
+}
+
+// 按顺序累计像素数，首次超过 `clipped` 的亮度级
+fn clip_point<'a>(bins: impl Iterator<Item = (usize, &'a u32)>, clipped: u32) -> f32 {
+    let mut count = 0;
+    for (i, &n) in bins {
+        count += n;
+        if count > clipped {
+            return i as f32;
+        }
+    }
+    0.0
+}
+
+// 由直方图得到均衡化查找表，`clip_limit` 为每个亮度级允许的最大像素数（超出部分平均分配到所有亮度级）
+fn equalization_lut(hist: &[u32; BINS], clip_limit: Option<f32>) -> [u8; BINS] {
+    let mut hist = hist.map(|n| n as f32);
+    if let Some(limit) = clip_limit {
+        let excess: f32 = hist.iter().map(|&n| (n - limit).max(0.0)).sum();
+        for n in &mut hist {
+            *n = n.min(limit) + excess / BINS as f32;
+        }
+    }
+
+    let total: f32 = hist.iter().sum();
+    let mut lut = [0u8; BINS];
+    if total <= 0.0 {
+        return std::array::from_fn(|i| i as u8);
+    }
+
+    // 以第一个非空亮度级为起点，使最暗的像素映射到 0
+    let first = hist.iter().copied().find(|&n| n > 0.0).unwrap_or(0.0);
+    let mut cdf = 0.0;
+    for (l, &n) in lut.iter_mut().zip(hist.iter()) {
+        cdf += n;
+        *l = if total > first {
+            ((cdf - first).max(0.0) / (total - first) * 255.0).round() as u8
+        } else {
+            255
+        };
+    }
+    lut
+}
+
+// 每个区块分别计算限制对比度的均衡化查找表，像素值在相邻四个区块的查找表之间双线性插值
+fn clahe(luminance: &mut [Option<u32>], width: u32, height: u32, clip_limit: f32, tiles: u32) {
+    let (tiles_x, tiles_y) = (tiles.clamp(1, width.max(1)), tiles.clamp(1, height.max(1)));
+    let tile_w = width as f32 / tiles_x as f32;
+    let tile_h = height as f32 / tiles_y as f32;
+
+    let mut luts = Vec::with_capacity((tiles_x * tiles_y) as usize);
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let (x0, x1) = ((tx as f32 * tile_w) as u32, ((tx + 1) as f32 * tile_w) as u32);
+            let (y0, y1) = ((ty as f32 * tile_h) as u32, ((ty + 1) as f32 * tile_h) as u32);
+            let tile = (y0..y1).flat_map(|y| (x0..x1).map(move |x| (y * width + x) as usize));
+            let hist = histogram(tile.map(|i| &luminance[i]));
+
+            let count: u32 = hist.iter().sum();
+            let limit = (clip_limit * count as f32 / BINS as f32).max(1.0);
+            luts.push(equalization_lut(&hist, Some(limit)));
+        }
+    }
+
+    let lut = |tx: u32, ty: u32, v: usize| luts[(ty * tiles_x + tx) as usize][v] as f32;
+    for y in 0..height {
+        // 相对区块中心的位置
+        let fy = ((y as f32 + 0.5) / tile_h - 0.5).clamp(0.0, (tiles_y - 1) as f32);
+        let (ty0, wy) = (fy.floor() as u32, fy.fract());
+        let ty1 = (ty0 + 1).min(tiles_y - 1);
+
+        for x in 0..width {
+            let Some(v) = &mut luminance[(y * width + x) as usize] else {
+                continue;
+            };
+
+            let fx = ((x as f32 + 0.5) / tile_w - 0.5).clamp(0.0, (tiles_x - 1) as f32);
+            let (tx0, wx) = (fx.floor() as u32, fx.fract());
+            let tx1 = (tx0 + 1).min(tiles_x - 1);
+
+            let i = (*v).min(255) as usize;
+            let top = lut(tx0, ty0, i) * (1.0 - wx) + lut(tx1, ty0, i) * wx;
+            let bottom = lut(tx0, ty1, i) * (1.0 - wx) + lut(tx1, ty1, i) * wx;
+            *v = (top * (1.0 - wy) + bottom * wy).round() as u32;
+        }
+    }
+}